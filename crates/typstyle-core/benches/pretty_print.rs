@@ -58,9 +58,60 @@ fn benchmark_pretty(c: &mut Criterion) {
     }
 }
 
+/// Builds an array nested `depth` levels deep, with `width` items at each level.
+fn nested_array(depth: usize, width: usize) -> String {
+    if depth == 0 {
+        return "x".to_string();
+    }
+    let item = nested_array(depth - 1, width);
+    format!("({})", vec![item; width].join(", "))
+}
+
+/// Builds a document with `sections` copies of a section with prose, code, tables, and math.
+fn large_document(sections: usize) -> String {
+    let section = r#"= Section
+
+Some text with *strong* and _emph_ words, and an equation $x^2 + y^2$ in it.
+- A list item with `raw` text.
++ An enum item.
+
+#let f(x, y) = {
+  let z = x + y
+  if z > 0 { z } else { -z }
+}
+
+#table(
+  columns: 3,
+  [a], [b], [c],
+  [d], [e], [f],
+)
+
+$ sum_(i = 1)^n i = (n (n + 1)) / 2 $
+"#;
+    section.repeat(sections)
+}
+
+fn benchmark_large(c: &mut Criterion) {
+    // About 5000 lines.
+    let source = Source::detached(large_document(250));
+    c.bench_function("attrs-large-document", |b| {
+        b.iter(|| AttrStore::new(source.root()))
+    });
+    c.bench_function("pretty-large-document", |b| {
+        b.iter(|| Typstyle::default().format_source(&source).unwrap())
+    });
+}
+
+fn benchmark_nested(c: &mut Criterion) {
+    let source = Source::detached(format!("#let x = {}\n", nested_array(6, 3)));
+    c.bench_function("pretty-nested-array", |b| {
+        b.iter(|| Typstyle::default().format_source(&source).unwrap())
+    });
+}
+
 criterion_group! {
     name = benches;
     config = Criterion::default();
-    targets = benchmark_attrs, benchmark_pretty
+    targets = benchmark_attrs, benchmark_pretty, benchmark_large, benchmark_nested
 }
 criterion_main!(benches);
@@ -1,7 +1,9 @@
+use std::ops::Range;
+
 use rustc_hash::FxHashMap;
 
 use typst_syntax::{
-    ast::{Args, AstNode, Math, Raw},
+    ast::{Math, Raw},
     Span, SyntaxKind, SyntaxNode,
 };
 
@@ -14,7 +16,8 @@ struct State {
 
 #[derive(Debug, Clone, Default)]
 pub struct Attributes {
-    /// Indicates whether formatting is explicitly disabled (`@typstyle off`) or always ignored.
+    /// Indicates whether formatting is explicitly disabled (`@typstyle off` or `@typstyle skip`)
+    /// or always ignored.
     pub(self) is_format_disabled: bool,
 
     /// Indicates whether any child node contains a comment.
@@ -27,6 +30,12 @@ pub struct Attributes {
     /// Indicates whether the node has a multiline "flavor",
     /// determined by the first space child containing a linebreak.
     pub(self) is_multiline_flavor: bool,
+
+    /// The maximum width for the node, set by a `@typstyle width <n>` comment before it.
+    pub(self) width_override: Option<usize>,
+
+    /// The column of a raw block in the source, if it starts a line.
+    pub(self) raw_indent: Option<usize>,
 }
 
 /// A storage structure that manages formatting attributes for syntax nodes.
@@ -45,6 +54,7 @@ impl AttrStore {
         };
         store.compute_no_format(node);
         store.compute_multiline(node);
+        store.compute_raw_indent(node);
         store
     }
 
@@ -54,6 +64,9 @@ impl AttrStore {
     }
 
     /// Checks if a given syntax node or any of its descendants contains a linebreak.
+    ///
+    /// Like other attributes, this is computed for the whole tree in `AttrStore::new`, so it
+    /// takes constant time.
     pub fn is_multiline(&self, node: &SyntaxNode) -> bool {
         self.check_node_attr(node, |attr| attr.is_multiline)
     }
@@ -74,6 +87,51 @@ impl AttrStore {
         self.check_node_attr(node, |attr| attr.is_format_disabled || attr.has_comment)
     }
 
+    /// Gets the maximum width set by a `@typstyle width <n>` comment for a given syntax node.
+    pub fn width_override(&self, node: &SyntaxNode) -> Option<usize> {
+        self.attr_map
+            .get(&node.span())
+            .and_then(|attr| attr.width_override)
+    }
+
+    /// Gets the column of a raw block in the source, if it starts a line.
+    pub fn raw_indent(&self, node: &SyntaxNode) -> Option<usize> {
+        self.attr_map
+            .get(&node.span())
+            .and_then(|attr| attr.raw_indent)
+    }
+
+    /// Gets the byte ranges of the nodes left as they are, e.g., by `@typstyle off` or
+    /// `@typstyle skip`. The root must be the node this store is created from.
+    ///
+    /// A disabled node covers all its descendants, and adjacent ranges are merged.
+    pub fn disabled_ranges(&self, root: &SyntaxNode) -> Vec<Range<usize>> {
+        fn collect(
+            store: &AttrStore,
+            node: &SyntaxNode,
+            offset: usize,
+            ranges: &mut Vec<Range<usize>>,
+        ) {
+            if store.is_format_disabled(node) {
+                let range = offset..offset + node.len();
+                match ranges.last_mut() {
+                    Some(last) if last.end == range.start => last.end = range.end,
+                    _ => ranges.push(range),
+                }
+                return;
+            }
+            let mut offset = offset;
+            for child in node.children() {
+                collect(store, child, offset, ranges);
+                offset += child.len();
+            }
+        }
+
+        let mut ranges = vec![];
+        collect(self, root, 0, &mut ranges);
+        ranges
+    }
+
     fn check_node_attr(&self, node: &SyntaxNode, pred: impl FnOnce(&Attributes) -> bool) -> bool {
         self.attr_map.get(&node.span()).is_some_and(pred)
     }
@@ -117,11 +175,28 @@ impl AttrStore {
             .is_multiline_flavor = true;
     }
 
+    fn compute_raw_indent(&mut self, root: &SyntaxNode) {
+        self.compute_raw_indent_impl(root, &mut LineIndent::default());
+    }
+
+    fn compute_raw_indent_impl(&mut self, node: &SyntaxNode, line: &mut LineIndent) {
+        if line.in_indent && node.cast::<Raw>().is_some_and(|raw| raw.block()) {
+            self.attr_map.entry(node.span()).or_default().raw_indent = Some(line.indent);
+        }
+        if node.children().len() == 0 {
+            line.advance(node.text());
+        }
+        for child in node.children() {
+            self.compute_raw_indent_impl(child, line);
+        }
+    }
+
     fn compute_no_format(&mut self, root: &SyntaxNode) {
-        self.compute_no_format_impl(root, State { is_math: false });
+        self.compute_no_format_impl(root, State { is_math: false }, false);
     }
 
-    fn compute_no_format_impl(&mut self, node: &SyntaxNode, state: State) {
+    /// `disabled_before` is set if a `@typstyle off` comment precedes the node.
+    fn compute_no_format_impl(&mut self, node: &SyntaxNode, state: State, disabled_before: bool) {
         let state = if node.is::<Math>() {
             State { is_math: true }
         } else {
@@ -136,25 +211,66 @@ impl AttrStore {
             self.set_format_disabled(node);
             return;
         }
-        // no format args in math blocks
-        if node.kind() == SyntaxKind::Args && state.is_math {
-            self.set_format_disabled(node);
-            return;
-        }
 
-        let mut disable_next = false;
+        let children = node.children().as_slice();
+        let mut disable_next = disabled_before;
+        // The index of the `@typstyle on` comment that ends the current disabled region.
+        let mut region_end = disabled_before
+            .then(|| find_format_on(children, 0))
+            .flatten();
         let mut commented = false;
-        for child in node.children() {
+        // The width set by a `@typstyle width` comment for the next node.
+        let mut next_width = None;
+        for (i, child) in children.iter().enumerate() {
             let child_kind = child.kind();
-            if child_kind == SyntaxKind::LineComment || child_kind == SyntaxKind::BlockComment {
+            if is_comment(child) {
                 commented = true;
-                // @typstyle off affects the whole next block
+                // @typstyle off affects the whole next block,
+                // or all siblings until @typstyle on if there is one.
                 if child.text().contains("@typstyle off") {
                     disable_next = true;
                     self.set_format_disabled(child);
+                    region_end = find_format_on(children, i + 1);
+                } else if child.text().contains("@typstyle skip") {
+                    // @typstyle skip only affects the next node, regardless of @typstyle on
+                    disable_next = true;
+                    region_end = None;
+                    self.set_format_disabled(child);
+                } else if child.text().contains("@typstyle on") {
+                    disable_next = false;
+                    region_end = None;
+                } else if let Some(width) = parse_width_override(child.text()) {
+                    next_width = Some(width);
                 }
                 continue;
             }
+            if !matches!(child_kind, SyntaxKind::Space | SyntaxKind::Hash) {
+                if let Some(width) = next_width.take() {
+                    self.set_width_override(child, width);
+                }
+            }
+            // no format nodes in the region between @typstyle off and @typstyle on,
+            // including spaces between them
+            if region_end.is_some_and(|end| i < end) {
+                let disabled = match child_kind {
+                    SyntaxKind::Space | SyntaxKind::Parbreak => {
+                        !(is_comment(&children[i + 1]) || i > 0 && is_comment(&children[i - 1]))
+                    }
+                    SyntaxKind::Hash => false,
+                    _ => true,
+                };
+                if disabled {
+                    self.set_format_disabled(child);
+                }
+                continue;
+            }
+            // A comment before the first statement of a code block is not part of the `Code`
+            // node, so the statements inside are disabled instead of the whole block.
+            if disable_next && child_kind == SyntaxKind::Code {
+                disable_next = false;
+                self.compute_no_format_impl(child, state, true);
+                continue;
+            }
             // no format nodes with @typstyle off
             if disable_next && !matches!(child_kind, SyntaxKind::Space | SyntaxKind::Hash) {
                 self.set_format_disabled(child);
@@ -166,7 +282,7 @@ impl AttrStore {
                 self.set_format_disabled(node);
                 break;
             }
-            self.compute_no_format_impl(child, state);
+            self.compute_no_format_impl(child, state, false);
         }
         if commented {
             self.set_commented(node);
@@ -183,14 +299,169 @@ impl AttrStore {
     fn set_commented(&mut self, node: &SyntaxNode) {
         self.attr_map.entry(node.span()).or_default().has_comment = true;
     }
+
+    fn set_width_override(&mut self, node: &SyntaxNode, width: usize) {
+        self.attr_map.entry(node.span()).or_default().width_override = Some(width);
+    }
+}
+
+/// The indentation of the current line while walking through the source text.
+struct LineIndent {
+    indent: usize,
+    /// Whether only whitespace is seen on the line so far.
+    in_indent: bool,
+}
+
+impl Default for LineIndent {
+    fn default() -> Self {
+        Self {
+            indent: 0,
+            in_indent: true,
+        }
+    }
+}
+
+impl LineIndent {
+    fn advance(&mut self, text: &str) {
+        let rest = match text.rsplit_once('\n') {
+            Some((_, rest)) => {
+                *self = Self::default();
+                rest
+            }
+            None => text,
+        };
+        if self.in_indent {
+            let leading = rest.chars().take_while(|c| c.is_whitespace()).count();
+            self.indent += leading;
+            self.in_indent = leading == rest.chars().count();
+        }
+    }
+}
+
+/// Parses the width in a `@typstyle width <n>` comment.
+fn parse_width_override(comment: &str) -> Option<usize> {
+    let (_, rest) = comment.split_once("@typstyle width")?;
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+/// Finds the index of the first `@typstyle on` comment in `nodes` at or after `start`.
+fn find_format_on(nodes: &[SyntaxNode], start: usize) -> Option<usize> {
+    nodes[start..]
+        .iter()
+        .position(|node| is_comment(node) && node.text().contains("@typstyle on"))
+        .map(|pos| start + pos)
+}
+
+fn is_comment(node: &SyntaxNode) -> bool {
+    matches!(
+        node.kind(),
+        SyntaxKind::LineComment | SyntaxKind::BlockComment
+    )
 }
 
-#[allow(unused)]
-fn is_2d_arg(arg: Args) -> bool {
-    for child in arg.to_untyped().children() {
-        if child.kind() == SyntaxKind::Semicolon {
-            return true;
+#[cfg(test)]
+mod tests {
+    use typst_syntax::{Source, SyntaxKind, SyntaxNode};
+
+    use super::AttrStore;
+
+    /// Get the kinds of non-comment nodes with formatting disabled, in preorder.
+    fn disabled_kinds(content: &str) -> Vec<SyntaxKind> {
+        fn collect(node: &SyntaxNode, store: &AttrStore, kinds: &mut Vec<SyntaxKind>) {
+            if store.is_format_disabled(node) && !node.kind().is_trivia() {
+                kinds.push(node.kind());
+            }
+            for child in node.children() {
+                collect(child, store, kinds);
+            }
+        }
+        let source = Source::detached(content);
+        let store = AttrStore::new(source.root());
+        let mut kinds = vec![];
+        collect(source.root(), &store, &mut kinds);
+        kinds
+    }
+
+    #[test]
+    fn test_format_disabled_in_markup() {
+        assert_eq!(
+            disabled_kinds("// @typstyle off\n#let x  =  1\n#let y  =  2\n"),
+            vec![SyntaxKind::LetBinding]
+        );
+        assert_eq!(
+            disabled_kinds("// @typstyle off\n*a  b* c\n"),
+            vec![SyntaxKind::Strong]
+        );
+    }
+
+    #[test]
+    fn test_format_disabled_region() {
+        assert_eq!(
+            disabled_kinds(
+                "// @typstyle off\n#let x  =  1\n#let y  =  2\n// @typstyle on\n#let z  =  3\n"
+            ),
+            vec![SyntaxKind::LetBinding, SyntaxKind::LetBinding]
+        );
+        // Without @typstyle on, only the next node is disabled.
+        assert_eq!(
+            disabled_kinds("#{\n  // @typstyle off\n  let x  =  1\n  let y  =  2\n}\n"),
+            vec![SyntaxKind::LetBinding]
+        );
+    }
+
+    #[test]
+    fn test_format_skip() {
+        assert_eq!(
+            disabled_kinds("// @typstyle skip\n#f(a,b)\n#f(a,b)\n// @typstyle on\n"),
+            vec![SyntaxKind::FuncCall]
+        );
+    }
+
+    #[test]
+    fn test_format_disabled_in_block() {
+        // Only the next sibling is disabled, not the enclosing block.
+        assert_eq!(
+            disabled_kinds("#[\n  // @typstyle off\n  *a  b*\n  c\n]\n"),
+            vec![SyntaxKind::Strong]
+        );
+        assert_eq!(
+            disabled_kinds("#{\n  // @typstyle off\n  let x  =  1\n  let y  =  2\n}\n"),
+            vec![SyntaxKind::LetBinding]
+        );
+        assert_eq!(
+            disabled_kinds(
+                "#{\n  // @typstyle off\n  let x  =  1\n  let y  =  2\n  // @typstyle on\n  z\n}\n"
+            ),
+            vec![SyntaxKind::LetBinding, SyntaxKind::LetBinding]
+        );
+    }
+
+    #[test]
+    fn test_disabled_ranges() {
+        let source = Source::detached("#f(a)\n// @typstyle off\n#let x  =  1\n$f(a,b)$\n");
+        let store = AttrStore::new(source.root());
+        assert_eq!(store.disabled_ranges(source.root()), vec![6..22, 24..35]);
+    }
+
+    #[test]
+    fn test_multiline_nested() {
+        fn count_nodes(node: &SyntaxNode) -> usize {
+            1 + node.children().map(count_nodes).sum::<usize>()
         }
+        fn check_multiline(node: &SyntaxNode, store: &AttrStore) {
+            if node.kind() == SyntaxKind::Parenthesized {
+                assert!(store.is_multiline(node));
+            }
+            node.children()
+                .for_each(|child| check_multiline(child, store));
+        }
+
+        // Each node is visited once, with at most one entry per node.
+        let depth = 50;
+        let content = format!("#{}x\n{}\n", "(".repeat(depth), ")".repeat(depth));
+        let source = Source::detached(content);
+        let store = AttrStore::new(source.root());
+        assert!(store.attr_map.len() <= count_nodes(source.root()));
+        check_multiline(source.root(), &store);
     }
-    false
 }
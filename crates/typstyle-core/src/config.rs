@@ -1,13 +1,101 @@
 /// Configuration Options for Typstyle Printer.
+///
+/// When (de)serialized, fields keep their snake_case names, and missing fields take their default
+/// values.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Config {
     /// Number of spaces per tab
+    #[cfg_attr(feature = "serde", serde(alias = "indent"))]
     pub tab_spaces: usize,
     /// Maximum width of each line.
     pub max_width: usize,
+    /// Maximum width of code embedded in markup, e.g., `#f(x)`. Uses `max_width` if not set.
+    pub code_width: Option<usize>,
+    /// Maximum width of equations in markup. Uses `max_width` if not set.
+    pub math_width: Option<usize>,
     /// Maximum number of blank lines which can be put between items.
     pub blank_lines_upper_bound: usize,
+    /// Line ending style of the output.
+    pub line_ending: LineEnding,
+    /// Whether to end the output with a newline.
+    pub final_newline: FinalNewline,
+    /// Whether to sort import items lexicographically.
+    pub sort_imports: bool,
+    /// Whether to remove repeated import items, keeping the first occurrence of each.
+    pub dedup_imports: bool,
+    /// Whether to sort the entries of dictionaries by key. Entries are never moved across spreads.
+    pub sort_dict_keys: bool,
+    /// How to print string literals.
+    pub quote_style: QuoteStyle,
+    /// Whether to remove redundant leading zeros of numbers, e.g., `007` to `7` and `050%` to
+    /// `50%`. Units are kept, and numbers with a base prefix like `0x` are left as they are.
+    pub normalize_numbers: bool,
+    /// Whether to collapse consecutive spaces in markup lines containing text.
+    pub collapse_spaces: bool,
+    /// Whether to rewrap paragraphs in markup to fit the maximum width.
+    pub reflow_prose: bool,
+    /// When to add trailing commas in arrays, dictionaries, and arguments.
+    pub trailing_comma: TrailingComma,
+    /// Whether a trailing comma in arrays, dictionaries, and arguments keeps them broken, even
+    /// if they fit in one line, e.g., `(1, 2,)`. Ignored if `trailing_comma` is `Always`, and for
+    /// arrays with one item, which need the comma.
+    pub magic_trailing_comma: bool,
+    /// Where to break lines around binary operators.
+    pub operator_break: OperatorBreak,
+    /// How to separate the statements of code blocks put in one line.
+    pub code_statement_separator: StatementSeparator,
+    /// Whether to align trailing comments of consecutive statements in code blocks.
+    pub align_comments: bool,
+    /// Whether to remove parentheses around single-atom scripts in math, e.g., `x^(2)` to `x^2`.
+    pub strip_attach_parens: bool,
+    /// Whether to put spaces around binary operators and relations in math.
+    pub math_operator_spacing: bool,
+    /// How to print the spaces inside parentheses and brackets in math.
+    pub math_delim_spacing: MathDelimSpacing,
+    /// Whether to print the attachments in math in a canonical order: primes, subscript, and then
+    /// superscript, e.g., `x^2_i` to `x_i^2`.
+    pub sort_math_attachments: bool,
+    /// How to write the shorthands in math, e.g., `->`, `→`, or `arrow.r`.
+    pub math_symbol_style: MathSymbolStyle,
+    /// Whether to put the args of function calls in block equations one per line when they
+    /// overflow, like in code. Args with rows, like `mat(1, 2; 3, 4)`, are broken into rows
+    /// regardless.
+    pub break_math_args: bool,
+    /// Whether to pad the lines of multiline block equations so that their alignment points
+    /// (`&`) are in the same columns.
+    pub align_math: bool,
+    /// Whether to renumber enum items with explicit numbers, so that they ascend from the first
+    /// item of each enum.
+    pub renumber_enums: bool,
+    /// Whether to pad the terms of consecutive term items, so that their descriptions start at
+    /// the same column.
+    pub align_terms: bool,
+    /// Minimum number of `.` in a dot chain to always break it, with one `.` per line,
+    /// even if it fits in one line. `0` means dot chains only break when they overflow.
+    pub dot_chain_threshold: usize,
+    /// Whether to ensure a space after `//` in line comments, e.g., `//foo` to `// foo`.
+    /// Comments starting with `///` or `//!` are kept.
+    pub line_comment_space: bool,
+    /// Whether to pad the cells of reflowed tables and grids, so that their columns line up.
+    pub align_table_cells: bool,
+    /// Names of functions to format like `table` and `grid`, matched against the callee
+    /// identifier, or the field name if called through a field access like `mypkg.table`.
+    pub extra_table_functions: Vec<String>,
+    /// Whether to remove the whitespace in content blocks that contain only whitespace,
+    /// e.g., `[ ]` to `[]`.
+    pub collapse_empty_content_blocks: bool,
+    /// Whether to remove parentheses around atomic expressions in code, e.g., `(x)` to `x`.
+    pub remove_redundant_parens: bool,
+    /// Whether to put everything in as few lines as possible, ignoring `max_width` and the
+    /// original linebreaks in lists. Required linebreaks, such as those in raw blocks, multiline
+    /// strings, and between paragraphs or statements, are kept.
+    pub compact: bool,
+    /// Whether to pack as many items of arrays and arguments in each line as fit when they
+    /// overflow, instead of putting each item on its own line. Lists broken in the source are
+    /// packed as well.
+    pub fill_lists: bool,
 }
 
 impl Default for Config {
@@ -15,7 +103,40 @@ impl Default for Config {
         Self {
             tab_spaces: 2,
             max_width: 80,
+            code_width: None,
+            math_width: None,
             blank_lines_upper_bound: 2,
+            line_ending: LineEnding::default(),
+            final_newline: FinalNewline::default(),
+            sort_imports: false,
+            dedup_imports: false,
+            sort_dict_keys: false,
+            quote_style: QuoteStyle::default(),
+            normalize_numbers: false,
+            collapse_spaces: false,
+            reflow_prose: false,
+            trailing_comma: TrailingComma::default(),
+            magic_trailing_comma: false,
+            operator_break: OperatorBreak::default(),
+            code_statement_separator: StatementSeparator::default(),
+            align_comments: false,
+            strip_attach_parens: false,
+            math_operator_spacing: false,
+            math_delim_spacing: MathDelimSpacing::default(),
+            sort_math_attachments: false,
+            math_symbol_style: MathSymbolStyle::default(),
+            break_math_args: false,
+            align_math: false,
+            renumber_enums: false,
+            align_terms: false,
+            dot_chain_threshold: 0,
+            line_comment_space: false,
+            align_table_cells: false,
+            extra_table_functions: Vec::new(),
+            collapse_empty_content_blocks: false,
+            remove_redundant_parens: false,
+            compact: false,
+            fill_lists: false,
         }
     }
 }
@@ -25,18 +146,383 @@ impl Config {
         Default::default()
     }
 
+    /// Parses a configuration from the content of a TOML file, such as `typstyle.toml`.
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
+
+    /// The width to render documents with, which is unlimited in compact mode.
+    pub(crate) fn render_width(&self) -> usize {
+        if self.compact {
+            usize::MAX
+        } else {
+            self.max_width
+        }
+    }
+
     pub fn with_width(mut self, max_width: usize) -> Self {
         self.max_width = max_width;
         self
     }
 
+    pub fn with_code_width(mut self, code_width: usize) -> Self {
+        self.code_width = Some(code_width);
+        self
+    }
+
+    pub fn with_math_width(mut self, math_width: usize) -> Self {
+        self.math_width = Some(math_width);
+        self
+    }
+
     pub fn with_tab_spaces(mut self, tab_spaces: usize) -> Self {
         self.tab_spaces = tab_spaces;
         self
     }
 
+    pub fn with_blank_lines_upper_bound(mut self, blank_lines_upper_bound: usize) -> Self {
+        self.blank_lines_upper_bound = blank_lines_upper_bound;
+        self
+    }
+
+    pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    pub fn with_final_newline(mut self, final_newline: FinalNewline) -> Self {
+        self.final_newline = final_newline;
+        self
+    }
+
+    pub fn with_sort_imports(mut self, sort_imports: bool) -> Self {
+        self.sort_imports = sort_imports;
+        self
+    }
+
+    pub fn with_dedup_imports(mut self, dedup_imports: bool) -> Self {
+        self.dedup_imports = dedup_imports;
+        self
+    }
+
+    pub fn with_sort_dict_keys(mut self, sort_dict_keys: bool) -> Self {
+        self.sort_dict_keys = sort_dict_keys;
+        self
+    }
+
+    pub fn with_quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.quote_style = quote_style;
+        self
+    }
+
+    pub fn with_normalize_numbers(mut self, normalize_numbers: bool) -> Self {
+        self.normalize_numbers = normalize_numbers;
+        self
+    }
+
+    pub fn with_collapse_spaces(mut self, collapse_spaces: bool) -> Self {
+        self.collapse_spaces = collapse_spaces;
+        self
+    }
+
+    pub fn with_reflow_prose(mut self, reflow_prose: bool) -> Self {
+        self.reflow_prose = reflow_prose;
+        self
+    }
+
+    pub fn with_trailing_comma(mut self, trailing_comma: TrailingComma) -> Self {
+        self.trailing_comma = trailing_comma;
+        self
+    }
+
+    pub fn with_magic_trailing_comma(mut self, magic_trailing_comma: bool) -> Self {
+        self.magic_trailing_comma = magic_trailing_comma;
+        self
+    }
+
+    pub fn with_operator_break(mut self, operator_break: OperatorBreak) -> Self {
+        self.operator_break = operator_break;
+        self
+    }
+
+    pub fn with_code_statement_separator(
+        mut self,
+        code_statement_separator: StatementSeparator,
+    ) -> Self {
+        self.code_statement_separator = code_statement_separator;
+        self
+    }
+
+    pub fn with_align_comments(mut self, align_comments: bool) -> Self {
+        self.align_comments = align_comments;
+        self
+    }
+
+    pub fn with_strip_attach_parens(mut self, strip_attach_parens: bool) -> Self {
+        self.strip_attach_parens = strip_attach_parens;
+        self
+    }
+
+    pub fn with_math_operator_spacing(mut self, math_operator_spacing: bool) -> Self {
+        self.math_operator_spacing = math_operator_spacing;
+        self
+    }
+
+    pub fn with_math_delim_spacing(mut self, math_delim_spacing: MathDelimSpacing) -> Self {
+        self.math_delim_spacing = math_delim_spacing;
+        self
+    }
+
+    pub fn with_sort_math_attachments(mut self, sort_math_attachments: bool) -> Self {
+        self.sort_math_attachments = sort_math_attachments;
+        self
+    }
+
+    pub fn with_math_symbol_style(mut self, math_symbol_style: MathSymbolStyle) -> Self {
+        self.math_symbol_style = math_symbol_style;
+        self
+    }
+
+    pub fn with_break_math_args(mut self, break_math_args: bool) -> Self {
+        self.break_math_args = break_math_args;
+        self
+    }
+
+    pub fn with_align_math(mut self, align_math: bool) -> Self {
+        self.align_math = align_math;
+        self
+    }
+
+    pub fn with_renumber_enums(mut self, renumber_enums: bool) -> Self {
+        self.renumber_enums = renumber_enums;
+        self
+    }
+
+    pub fn with_align_terms(mut self, align_terms: bool) -> Self {
+        self.align_terms = align_terms;
+        self
+    }
+
+    pub fn with_dot_chain_threshold(mut self, dot_chain_threshold: usize) -> Self {
+        self.dot_chain_threshold = dot_chain_threshold;
+        self
+    }
+
+    pub fn with_line_comment_space(mut self, line_comment_space: bool) -> Self {
+        self.line_comment_space = line_comment_space;
+        self
+    }
+
+    pub fn with_align_table_cells(mut self, align_table_cells: bool) -> Self {
+        self.align_table_cells = align_table_cells;
+        self
+    }
+
+    pub fn with_extra_table_functions(mut self, extra_table_functions: Vec<String>) -> Self {
+        self.extra_table_functions = extra_table_functions;
+        self
+    }
+
+    pub fn with_collapse_empty_content_blocks(
+        mut self,
+        collapse_empty_content_blocks: bool,
+    ) -> Self {
+        self.collapse_empty_content_blocks = collapse_empty_content_blocks;
+        self
+    }
+
+    pub fn with_remove_redundant_parens(mut self, remove_redundant_parens: bool) -> Self {
+        self.remove_redundant_parens = remove_redundant_parens;
+        self
+    }
+
+    pub fn with_compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    pub fn with_fill_lists(mut self, fill_lists: bool) -> Self {
+        self.fill_lists = fill_lists;
+        self
+    }
+
     pub fn chain_width(&self) -> usize {
         const CHAIN_WIDTH_RATIO: f32 = 0.6;
         (self.max_width as f32 * CHAIN_WIDTH_RATIO) as usize
     }
 }
+
+/// Line ending style of the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum LineEnding {
+    /// Line feed (`\n`).
+    #[default]
+    Lf,
+    /// Carriage return and line feed (`\r\n`).
+    Crlf,
+    /// Follow the first linebreak in the input. Falls back to `Lf` if there is none.
+    Auto,
+}
+
+impl LineEnding {
+    /// Resolve the actual newline sequence for the given input.
+    pub fn resolve(self, input: &str) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+            Self::Auto => match input.find('\n') {
+                Some(i) if input[..i].ends_with('\r') => "\r\n",
+                _ => "\n",
+            },
+        }
+    }
+}
+
+/// Whether to end the output with a newline.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum FinalNewline {
+    /// Always end with a newline.
+    #[default]
+    Always,
+    /// Never end with a newline.
+    Never,
+    /// End with a newline if and only if the input does.
+    Preserve,
+}
+
+impl FinalNewline {
+    /// Resolve whether to end the output with a newline for the given input.
+    pub fn resolve(self, input: &str) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Preserve => input.ends_with('\n'),
+        }
+    }
+}
+
+/// Style of string literals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum QuoteStyle {
+    /// Keep strings as is.
+    #[default]
+    Preserve,
+    /// Replace unnecessary escapes in single-line strings with literal characters. Escapes of
+    /// quotes, backslashes, control, whitespace, and invisible characters are kept.
+    Normalize,
+}
+
+/// Policy of trailing commas.
+///
+/// Trailing commas that are syntactically required, such as in `(1,)`, are always kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TrailingComma {
+    /// Always add trailing commas.
+    Always,
+    /// Never add trailing commas.
+    Never,
+    /// Add trailing commas only when the list is broken into multiple lines.
+    #[default]
+    Multiline,
+}
+
+/// Position of linebreaks around binary operators.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OperatorBreak {
+    /// Break before operators, putting them at the start of continuation lines.
+    #[default]
+    Before,
+    /// Break after operators, leaving them at the end of lines.
+    After,
+}
+
+/// Separator between the statements of code blocks put in one line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum StatementSeparator {
+    /// Always put statements on separate lines. Only code blocks with at most one statement are
+    /// put in one line.
+    #[default]
+    Newline,
+    /// Join the statements with `; ` if they fit in one line, e.g., `{ let x = 1; x }`. No
+    /// semicolons are added when the statements are put on separate lines.
+    Semicolon,
+}
+
+/// Spacing inside parentheses and brackets in math, e.g., `( x )` or `(x)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MathDelimSpacing {
+    /// Keep a space after the opening delimiter and before the closing one only if present.
+    #[default]
+    Preserve,
+    /// Always put spaces inside the delimiters, e.g., `( x )`.
+    Spaced,
+    /// Never put spaces inside the delimiters, e.g., `(x)`.
+    Tight,
+}
+
+/// The notation of shorthands in math, such as `->`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MathSymbolStyle {
+    /// Keep the shorthands as they are written.
+    #[default]
+    Preserve,
+    /// Write the shorthands as the Unicode symbols they stand for, e.g., `→`.
+    Unicode,
+    /// Write the shorthands as named symbols, e.g., `arrow.r`.
+    Named,
+}
+
+#[cfg(all(test, feature = "toml"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_toml_str() {
+        let config = Config::from_toml_str(
+            r#"
+max_width = 100
+indent = 4
+blank_lines_upper_bound = 1
+trailing_comma = "never"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config::new()
+                .with_width(100)
+                .with_tab_spaces(4)
+                .with_blank_lines_upper_bound(1)
+                .with_trailing_comma(TrailingComma::Never)
+        );
+
+        assert_eq!(Config::from_toml_str("").unwrap(), Config::default());
+        assert!(Config::from_toml_str("max_width = \"wide\"").is_err());
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let config = Config::default();
+        let s = toml::to_string(&config).unwrap();
+        assert!(s.contains("max_width = 80"));
+        assert!(s.contains("trailing_comma = \"multiline\""));
+        assert_eq!(Config::from_toml_str(&s).unwrap(), config);
+    }
+}
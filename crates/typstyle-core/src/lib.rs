@@ -7,35 +7,97 @@ mod config;
 mod utils;
 
 pub use attr::AttrStore;
-pub use config::Config;
+pub use config::{
+    Config, FinalNewline, LineEnding, MathDelimSpacing, MathSymbolStyle, OperatorBreak, QuoteStyle,
+    StatementSeparator, TrailingComma,
+};
+
+use std::sync::Arc;
 
 use pretty::ArenaDoc;
 use pretty::PrettyPrinter;
+use rustc_hash::FxHashMap;
 use typst_syntax::Source;
 
 #[derive(Debug)]
 pub enum Error {
     SyntaxError,
+    /// Formatting the output again yields a different result.
+    NotIdempotent {
+        first: String,
+        second: String,
+    },
+    /// The formatting result has a different syntax tree from the original.
+    SemanticChange,
+    /// Writing the formatting result failed.
+    Io(std::io::Error),
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::SyntaxError => write!(f, "The document has syntax errors"),
+            Error::NotIdempotent { .. } => {
+                write!(f, "The formatting result changes when formatted again")
+            }
+            Error::SemanticChange => {
+                write!(
+                    f,
+                    "The formatting result changes the meaning of the document"
+                )
+            }
+            Error::Io(err) => write!(f, "Failed to write the formatting result: {err}"),
         }
     }
 }
 
+/// A formatter for the body of raw blocks. Returns `None` to keep the body unchanged.
+pub type RawFormatter = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Raw formatters registered by language tags.
+#[derive(Clone, Default)]
+pub(crate) struct RawFormatters(FxHashMap<String, RawFormatter>);
+
+impl RawFormatters {
+    pub(crate) fn get(&self, lang: &str) -> Option<&RawFormatter> {
+        self.0.get(lang)
+    }
+}
+
+impl std::fmt::Debug for RawFormatters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
 /// Entry point for pretty printing a typst document.
 #[derive(Debug, Clone, Default)]
 pub struct Typstyle {
     config: Config,
+    raw_formatters: RawFormatters,
 }
 
 impl Typstyle {
     /// Create Typstyle formatter with config.
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self {
+            config,
+            raw_formatters: RawFormatters::default(),
+        }
+    }
+
+    /// Register a formatter for the body of raw blocks with the given language tag, e.g., `json`.
+    ///
+    /// The body is replaced if the formatter returns `Some`.
+    pub fn with_raw_formatter(
+        mut self,
+        lang: &str,
+        formatter: impl Fn(&str) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.raw_formatters
+            .0
+            .insert(lang.to_string(), Arc::new(formatter));
+        self
     }
 
     /// Format typst content.
@@ -44,11 +106,90 @@ impl Typstyle {
         self.format_source(&Source::detached(content.into()))
     }
 
+    /// Format typst content, returning `None` if the result is identical to the input.
+    pub fn format_content_if_changed(
+        self,
+        content: impl Into<String>,
+    ) -> Result<Option<String>, Error> {
+        let content = content.into();
+        let formatted = self.format_content(content.as_str())?;
+        Ok((formatted != content).then_some(formatted))
+    }
+
+    /// Format typst content, and check that formatting the result again changes nothing.
+    pub fn format_content_checked(self, content: impl Into<String>) -> Result<String, Error> {
+        let first = self.clone().format_content(content)?;
+        let second = self.format_content(first.as_str())?;
+        if first != second {
+            return Err(Error::NotIdempotent { first, second });
+        }
+        Ok(first)
+    }
+
+    /// Format typst content, and check that the result means the same as the content.
+    ///
+    /// Both are parsed and their syntax trees are compared, ignoring comments, commas, semicolons,
+    /// and the amount of whitespace. Spaces and paragraph breaks are only significant in markup
+    /// and equations. Options that rewrite the document, such as `sort_imports`, are reported as
+    /// changes too.
+    ///
+    /// This is much slower than formatting alone, and is meant for debugging and CI.
+    pub fn format_content_verified(self, content: impl Into<String>) -> Result<String, Error> {
+        let source = Source::detached(content.into());
+        let formatted = self.format_source(&source)?;
+        let result = Source::detached(formatted.as_str());
+        if result.root().erroneous() || !utils::is_semantically_equal(source.root(), result.root())
+        {
+            return Err(Error::SemanticChange);
+        }
+        Ok(formatted)
+    }
+
     /// Format typst source.
     pub fn format_source(self, source: &Source) -> Result<String, Error> {
         self.format_source_inspect(source, |_| {})
     }
 
+    /// Format typst source, and write the result to the writer.
+    ///
+    /// The result is written line by line as it is rendered, without building the whole string
+    /// in memory like `format_source`.
+    pub fn format_to_writer(
+        self,
+        source: &Source,
+        writer: impl std::io::Write,
+    ) -> Result<(), Error> {
+        let root = source.root();
+        if root.erroneous() {
+            return Err(Error::SyntaxError);
+        }
+        let attr_store = AttrStore::new(root);
+        let printer = PrettyPrinter::new(self.config.clone(), &attr_store)
+            .with_raw_formatters(self.raw_formatters);
+        let doc = printer.convert_markup(root.cast().unwrap());
+        let mut writer =
+            utils::LineWriter::new(writer, self.config.line_ending.resolve(source.text()));
+        doc.render_raw(self.config.render_width(), &mut writer)
+            .and_then(|_| writer.finish(self.config.final_newline.resolve(source.text())))
+            .map_err(Error::Io)
+    }
+
+    /// Format multiple typst sources, returning the results in the same order.
+    ///
+    /// With the `parallel` feature, the sources are formatted in parallel.
+    pub fn format_many(&self, sources: &[Source]) -> Vec<Result<String, Error>> {
+        #[cfg(feature = "parallel")]
+        use rayon::prelude::*;
+
+        #[cfg(feature = "parallel")]
+        let sources = sources.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let sources = sources.iter();
+        sources
+            .map(|source| self.clone().format_source(source))
+            .collect()
+    }
+
     /// Format typst source, and inspect the pretty document.
     pub fn format_source_inspect(
         self,
@@ -60,13 +201,43 @@ impl Typstyle {
             return Err(Error::SyntaxError);
         }
         let attr_store = AttrStore::new(root);
-        let printer = PrettyPrinter::new(self.config.clone(), attr_store);
+        self.format_with_attrs_inspect(source, &attr_store, inspector)
+    }
+
+    /// Format typst source with the attributes of its syntax tree computed in advance.
+    ///
+    /// The attributes can be reused across calls as long as the source is unchanged.
+    /// They must be computed by `AttrStore::new(source.root())`.
+    pub fn format_with_attrs(
+        &self,
+        source: &Source,
+        attr_store: &AttrStore,
+    ) -> Result<String, Error> {
+        self.format_with_attrs_inspect(source, attr_store, |_| {})
+    }
+
+    fn format_with_attrs_inspect(
+        &self,
+        source: &Source,
+        attr_store: &AttrStore,
+        inspector: impl FnOnce(&ArenaDoc<'_>),
+    ) -> Result<String, Error> {
+        let root = source.root();
+        if root.erroneous() {
+            return Err(Error::SyntaxError);
+        }
+        let printer = PrettyPrinter::new(self.config.clone(), attr_store)
+            .with_raw_formatters(self.raw_formatters.clone());
         let markup = root.cast().unwrap();
         let doc = printer.convert_markup(markup);
         inspector(&doc);
-        let result = doc.pretty(self.config.max_width).to_string();
-        let result = utils::strip_trailing_whitespace(&result);
-        Ok(result)
+        let mut result = vec![];
+        let mut writer =
+            utils::LineWriter::new(&mut result, self.config.line_ending.resolve(source.text()));
+        doc.render_raw(self.config.render_width(), &mut writer)
+            .and_then(|_| writer.finish(self.config.final_newline.resolve(source.text())))
+            .map_err(Error::Io)?;
+        Ok(String::from_utf8(result).expect("the document is rendered from strings"))
     }
 }
 
@@ -80,6 +251,84 @@ pub fn format_with_width(content: &str, width: usize) -> String {
         .unwrap_or_else(|_| content.to_string())
 }
 
+/// Format typst content by Typstyle configured with a JSON-serialized [`Config`].
+///
+/// Missing fields in the config take their default values. On failure, it returns a JSON object
+/// `{ "kind": ..., "message": ... }`, where `kind` is `"config"` if the config is invalid, or
+/// `"format"` if the content cannot be formatted.
+#[cfg(feature = "wasm")]
+pub fn format_with_json_config(content: &str, config: &str) -> Result<String, String> {
+    let error_json = |kind: &str, message: String| {
+        serde_json::json!({ "kind": kind, "message": message }).to_string()
+    };
+    let config: Config =
+        serde_json::from_str(config).map_err(|err| error_json("config", err.to_string()))?;
+    Typstyle::new(config)
+        .format_content(content)
+        .map_err(|err| error_json("format", err.to_string()))
+}
+
+/// Output of [`format_with_diagnostics`], serialized as `{ ok, text, errors }`.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FormatOutput {
+    /// Whether the content is formatted successfully.
+    pub ok: bool,
+    /// The formatted text, or the original text if formatting failed.
+    pub text: String,
+    /// Syntax errors found in the content.
+    pub errors: Vec<SyntaxErrorInfo>,
+}
+
+/// A syntax error with its location. Lines and columns are zero-based, and columns are counted
+/// in characters.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SyntaxErrorInfo {
+    pub message: String,
+    /// Start position as `(line, column)`.
+    pub start: (usize, usize),
+    /// End position as `(line, column)`.
+    pub end: (usize, usize),
+}
+
+/// Format typst content, reporting syntax errors instead of failing.
+#[cfg(feature = "serde")]
+pub fn format_with_diagnostics(content: &str, config: Config) -> FormatOutput {
+    let source = Source::detached(content);
+    if let Ok(text) = Typstyle::new(config).format_source(&source) {
+        return FormatOutput {
+            ok: true,
+            text,
+            errors: vec![],
+        };
+    }
+    let position = |offset: usize| {
+        (
+            source.byte_to_line(offset).unwrap_or_default(),
+            source.byte_to_column(offset).unwrap_or_default(),
+        )
+    };
+    let errors = source
+        .root()
+        .errors()
+        .into_iter()
+        .map(|err| {
+            let range = source.range(err.span).unwrap_or_default();
+            SyntaxErrorInfo {
+                message: err.message.to_string(),
+                start: position(range.start),
+                end: position(range.end),
+            }
+        })
+        .collect();
+    FormatOutput {
+        ok: false,
+        text: content.to_string(),
+        errors,
+    }
+}
+
 #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
 use wasm_bindgen::prelude::*;
 
@@ -88,3 +337,368 @@ use wasm_bindgen::prelude::*;
 pub fn pretty_print_wasm(content: &str, width: usize) -> String {
     format_with_width(content, width)
 }
+
+/// Format typst content with a JSON-serialized config. Throws a JSON error string on failure.
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+#[wasm_bindgen]
+pub fn pretty_print_with_config_wasm(content: &str, config: &str) -> Result<String, String> {
+    format_with_json_config(content, config)
+}
+
+/// Format typst content with a JSON-serialized config, and return a JSON object
+/// `{ ok, text, errors }` (see [`FormatOutput`]). Throws a JSON error string if the config is
+/// invalid.
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+#[wasm_bindgen]
+pub fn pretty_print_with_diagnostics_wasm(content: &str, config: &str) -> Result<String, String> {
+    let config: Config = serde_json::from_str(config).map_err(|err| {
+        serde_json::json!({ "kind": "config", "message": err.to_string() }).to_string()
+    })?;
+    let output = format_with_diagnostics(content, config);
+    Ok(serde_json::to_string(&output).expect("format output is serializable"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Format content with the given config, which must succeed.
+    pub(crate) fn format(content: &str, config: Config) -> String {
+        Typstyle::new(config).format_content(content).unwrap()
+    }
+
+    #[test]
+    fn test_tab_spaces() {
+        let content = "#let f(x) = {\n  let y = x\n  [#y]\n}\n";
+        assert_eq!(format(content, Config::new().with_tab_spaces(2)), content);
+        assert_eq!(
+            format(content, Config::new().with_tab_spaces(4)),
+            "#let f(x) = {\n    let y = x\n    [#y]\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_to_writer() {
+        let content = "#let  x  =  1\r\n```\r\na  \r\n```\r\n\r\n\r\n\r\n= Heading\r\n";
+        let source = Source::detached(content);
+        for config in [
+            Config::new(),
+            Config::new().with_final_newline(FinalNewline::Never),
+            Config::new().with_line_ending(LineEnding::Auto),
+        ] {
+            let mut out = vec![];
+            Typstyle::new(config.clone())
+                .format_to_writer(&source, &mut out)
+                .unwrap();
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                Typstyle::new(config).format_source(&source).unwrap()
+            );
+        }
+
+        let source = Source::detached("#let x = (");
+        assert!(matches!(
+            Typstyle::default().format_to_writer(&source, std::io::sink()),
+            Err(Error::SyntaxError)
+        ));
+    }
+
+    #[test]
+    fn test_format_with_attrs() {
+        let typstyle = Typstyle::default();
+        let source = Source::detached("#let  x  =  1\n// @typstyle off\n#let  y  =  2\n");
+        let attr_store = AttrStore::new(source.root());
+        let expected = "#let x = 1\n// @typstyle off\n#let  y  =  2\n";
+        for _ in 0..2 {
+            assert_eq!(
+                typstyle.format_with_attrs(&source, &attr_store).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_many() {
+        let sources = ["#let  x  =  1\n", "#let", "#f( a )\n"].map(Source::detached);
+        let results = Typstyle::default().format_many(&sources);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_deref().unwrap(), "#let x = 1\n");
+        assert!(matches!(results[1], Err(Error::SyntaxError)));
+        assert_eq!(results[2].as_deref().unwrap(), "#f(a)\n");
+    }
+
+    #[test]
+    fn test_raw_formatter() {
+        let typstyle = Typstyle::default()
+            .with_raw_formatter("json", |body| {
+                Some(body.replace(':', ": ").replace(',', ",\n"))
+            })
+            .with_raw_formatter("toml", |_| None);
+        let format = |content: &str| typstyle.clone().format_content(content).unwrap();
+
+        let content = "#[\n  ```json\n  {\"a\":1,\"b\":2}\n  ```\n]\n";
+        assert_eq!(
+            format(content),
+            "#[\n  ```json\n  {\"a\": 1,\n  \"b\": 2}\n  ```\n]\n"
+        );
+        for content in ["```toml\na=1\n```\n", "```yaml\na:1\n```\n", "`a:1`\n"] {
+            assert_eq!(format(content), content);
+        }
+    }
+
+    #[test]
+    fn test_format_content_if_changed() {
+        let typstyle = Typstyle::default();
+        assert_eq!(
+            typstyle
+                .clone()
+                .format_content_if_changed("#let x  =  1\n")
+                .unwrap(),
+            Some("#let x = 1\n".to_string())
+        );
+        assert_eq!(
+            typstyle
+                .clone()
+                .format_content_if_changed("#let x = 1\n")
+                .unwrap(),
+            None
+        );
+        assert!(typstyle.format_content_if_changed("#let").is_err());
+    }
+
+    #[test]
+    fn test_format_content_verified() {
+        let verify =
+            |content: &str, config: Config| Typstyle::new(config).format_content_verified(content);
+        assert_eq!(
+            verify("#let  x  =  1\n#f( a,b, )\n", Config::new()).unwrap(),
+            "#let x = 1\n#f(a, b)\n"
+        );
+        let content = "foo\nbar // c\n\n$ a  +  b $ *x*\n";
+        assert_eq!(
+            verify(content, Config::new().with_reflow_prose(true)).unwrap(),
+            "foo bar // c\n\n$ a + b $ *x*\n"
+        );
+        assert!(matches!(
+            verify(
+                "#import \"a.typ\": b, a\n",
+                Config::new().with_sort_imports(true)
+            ),
+            Err(Error::SemanticChange)
+        ));
+        assert!(matches!(
+            verify("#let", Config::new()),
+            Err(Error::SyntaxError)
+        ));
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_format_with_json_config() {
+        assert_eq!(
+            format_with_json_config("#let f(x) = {\n  x\n}", r#"{ "tab_spaces": 4 }"#),
+            Ok("#let f(x) = {\n    x\n}\n".to_string())
+        );
+        let config_err = format_with_json_config("", r#"{ "max_width": "wide" }"#).unwrap_err();
+        assert!(config_err.starts_with(r#"{"kind":"config","#));
+        assert_eq!(
+            format_with_json_config("#let", "{}"),
+            Err(r#"{"kind":"format","message":"The document has syntax errors"}"#.to_string())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_format_with_diagnostics() {
+        let output = format_with_diagnostics("#let x  =  1", Config::new());
+        assert!(output.ok);
+        assert_eq!(output.text, "#let x = 1\n");
+        assert!(output.errors.is_empty());
+
+        let output = format_with_diagnostics("Hi\n#let", Config::new());
+        assert!(!output.ok);
+        assert_eq!(output.text, "Hi\n#let");
+        assert_eq!(
+            output.errors,
+            vec![SyntaxErrorInfo {
+                message: "expected pattern".to_string(),
+                start: (1, 4),
+                end: (1, 4),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_format_disabled_region() {
+        let content = "// @typstyle off\n#f( 1 )\n\n\n\n#f( 2 )\n// @typstyle on\n#f( 3 )\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "// @typstyle off\n#f( 1 )\n\n\n\n#f( 2 )\n// @typstyle on\n#f(3)\n"
+        );
+
+        let content = "#{\n  // @typstyle off\n  let x  =  1\n  let y  =  2\n}\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "#{\n  // @typstyle off\n  let x  =  1\n  let y = 2\n}\n"
+        );
+
+        // Regions in arrays and args are kept as is, with their separators.
+        let region = "  // @typstyle off\n  1,2,\n  3,4,\n  // @typstyle on\n";
+        assert_eq!(
+            format(&format!("#let a = (\n{region}  5,6,\n)\n"), Config::new()),
+            format!("#let a = (\n{region}  5,\n  6,\n)\n")
+        );
+        assert_eq!(
+            format(&format!("#f(\n{region}  5,6,\n)\n"), Config::new()),
+            format!("#f(\n{region}  5,\n  6,\n)\n")
+        );
+    }
+
+    #[test]
+    fn test_format_skip() {
+        let content = "// @typstyle skip\n#(1,2,  3)\n#(1,2,  3)\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "// @typstyle skip\n#(1,2,  3)\n#(1, 2, 3)\n"
+        );
+
+        let content = "// @typstyle skip\n#f(a,b)\n#f(a,b)\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "// @typstyle skip\n#f(a,b)\n#f(a, b)\n"
+        );
+
+        let content = "/* @typstyle skip */\n$x  +  y$\n$x  +  y$\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "/* @typstyle skip */\n$x  +  y$\n$x + y$\n"
+        );
+    }
+
+    #[test]
+    fn test_width_override() {
+        let config = Config::new().with_width(20);
+        let content = "#let x = (aaaa, bbbb)\n// @typstyle width 40\n#let y = (aaaa, bbbb)\n";
+        assert_eq!(
+            format(content, config.clone()),
+            "#let x = (\n  aaaa,\n  bbbb,\n)\n// @typstyle width 40\n#let y = (aaaa, bbbb)\n"
+        );
+
+        // Nested overrides apply to their own subtrees only.
+        let content = "// @typstyle width 100\n#{\n  let a = (aaaaaaaa, bbbbbbbb)\n  // @typstyle width 10\n  let b = (cccc, dddd)\n  let c = (cccc, dddd)\n}\n";
+        assert_eq!(
+            format(content, config),
+            "// @typstyle width 100\n#{\n  let a = (aaaaaaaa, bbbbbbbb)\n  // @typstyle width 10\n  let b = (\n    cccc,\n    dddd,\n  )\n  let c = (cccc, dddd)\n}\n"
+        );
+
+        // The width is counted from the start of the line, not from the indentation.
+        let content = "#[\n  // @typstyle width 18\n  #f(aaaa, bbbb, cc)\n]\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "#[\n  // @typstyle width 18\n  #f(\n    aaaa,\n    bbbb,\n    cc,\n  )\n]\n"
+        );
+        // Lines in raw blocks keep their indentation.
+        let content = "#[\n  // @typstyle width 16\n  #f(aaaa, ```\n  raw\n  ```)\n]\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "#[\n  // @typstyle width 16\n  #f(\n    aaaa,\n    ```\n  raw\n  ```,\n  )\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_mode_widths() {
+        let content = "#f(aaaa, bbbb, cccc)\n$ aaaa + bbbb + cccc $\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_code_width(16)),
+            "#f(\n  aaaa,\n  bbbb,\n  cccc,\n)\n$ aaaa + bbbb + cccc $\n"
+        );
+        assert_eq!(
+            format(content, Config::new().with_math_width(10)),
+            "#f(aaaa, bbbb, cccc)\n$\n  aaaa + bbbb + cccc\n$\n"
+        );
+        let config = Config::new().with_code_width(16).with_compact(true);
+        assert_eq!(format(content, config), content);
+
+        // The width includes the indentation of nested content.
+        let content = "#[\n  #f(aaaa, bbbb, cc)\n  - #f(aaaa, bbbb, cc)\n]\n";
+        assert_eq!(
+            format(content, Config::new().with_code_width(20)),
+            "#[\n  #f(aaaa, bbbb, cc)\n  - #f(\n      aaaa,\n      bbbb,\n      cc,\n    )\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_blank_lines_upper_bound() {
+        let content = "#{\n  let a = 1\n\n\n\n  let b = 2\n}\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "#{\n  let a = 1\n\n\n  let b = 2\n}\n"
+        );
+        assert_eq!(
+            format(content, Config::new().with_blank_lines_upper_bound(1)),
+            "#{\n  let a = 1\n\n  let b = 2\n}\n"
+        );
+        assert_eq!(
+            format(content, Config::new().with_blank_lines_upper_bound(0)),
+            "#{\n  let a = 1\n  let b = 2\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_line_ending() {
+        let content = "#let x  =  1\r\n#x\r\n";
+        let lf = Config::new().with_line_ending(LineEnding::Lf);
+        let crlf = Config::new().with_line_ending(LineEnding::Crlf);
+        let auto = Config::new().with_line_ending(LineEnding::Auto);
+        assert_eq!(format(content, lf), "#let x = 1\n#x\n");
+        assert_eq!(format(content, crlf), "#let x = 1\r\n#x\r\n");
+        assert_eq!(format(content, auto.clone()), "#let x = 1\r\n#x\r\n");
+        assert_eq!(format("#let x  =  1\n", auto), "#let x = 1\n");
+    }
+
+    #[test]
+    fn test_final_newline() {
+        let always = Config::new().with_final_newline(FinalNewline::Always);
+        let never = Config::new().with_final_newline(FinalNewline::Never);
+        let preserve = Config::new().with_final_newline(FinalNewline::Preserve);
+        assert_eq!(format("#let x  =  1", always), "#let x = 1\n");
+        assert_eq!(format("#let x  =  1\n\n", never), "#let x = 1");
+        assert_eq!(format("#let x  =  1", preserve.clone()), "#let x = 1");
+        assert_eq!(format("#let x  =  1\n", preserve), "#let x = 1\n");
+    }
+
+    #[test]
+    fn test_compact() {
+        let compact = Config::new().with_width(20).with_compact(true);
+        let content =
+            "#let x = (\n  1,\n  2,\n)\n#f(\n  aaaaaaaaaa,\n  bbbbbbbbbb,\n  cccccccccc,\n)\n";
+        assert_eq!(
+            format(content, compact.clone()),
+            "#let x = (1, 2)\n#f(aaaaaaaaaa, bbbbbbbbbb, cccccccccc)\n"
+        );
+        assert_eq!(
+            format("#table(columns: 2, [a], [b])\n", compact.clone()),
+            "#table(columns: 2, [a], [b])\n"
+        );
+        // Required linebreaks are kept.
+        for content in [
+            "#let f() = {\n  a\n  b\n}\n",
+            "a\n\nb\n",
+            "```rs\nfn main() {}\n```\n",
+        ] {
+            assert_eq!(format(content, compact.clone()), content);
+        }
+    }
+
+    #[test]
+    fn test_format_content_checked() {
+        let content = "#let x  =  (1,2,3)\n";
+        let res = Typstyle::default().format_content_checked(content).unwrap();
+        assert_eq!(res, "#let x = (1, 2, 3)\n");
+        assert!(matches!(
+            Typstyle::default().format_content_checked("#let x = ("),
+            Err(Error::SyntaxError)
+        ));
+    }
+}
@@ -1,7 +1,7 @@
 use std::ops::Range;
 
 use typst_syntax::{
-    ast::{Expr, Markup, Pattern},
+    ast::{AstNode, CodeBlock, Equation, Expr, Markup, Pattern},
     LinkedNode, Source, Span, SyntaxKind,
 };
 
@@ -9,6 +9,10 @@ use crate::{pretty::Mode, utils, AttrStore, Error, PrettyPrinter, Typstyle};
 
 impl Typstyle {
     /// Format the node with minimal span that covering the given range.
+    ///
+    /// If the range crosses the boundaries of sibling nodes, their enclosing node is formatted.
+    /// Returns the range of the formatted node and the formatted text to replace it. Lines after the
+    /// first are indented like the line where the node starts.
     pub fn format_source_range(
         &self,
         source: &Source,
@@ -24,25 +28,76 @@ impl Typstyle {
         };
 
         let attrs = AttrStore::new(node.get()); // Here we only compute the attributes of that subtree.
-        let printer = PrettyPrinter::new(self.config.clone(), attrs);
+        let printer = PrettyPrinter::new(self.config.clone(), &attrs)
+            .with_raw_formatters(self.raw_formatters.clone());
         printer.push_mode(mode);
         let doc = if let Some(markup) = node.cast() {
             printer.convert_markup(markup)
         } else if let Some(expr) = node.cast() {
-            printer.convert_expr(expr)
+            if node.parent_kind() == Some(SyntaxKind::Markup) {
+                printer.convert_embedded_expr(expr)
+            } else {
+                printer.convert_expr(expr)
+            }
         } else if let Some(pattern) = node.cast() {
             printer.convert_pattern(pattern)
         } else {
             return Err(Error::SyntaxError);
         };
-        // Infer indent from context.
-        let indent = utils::count_spaces_after_last_newline(source.text(), range.start);
+        // Infer indent from the line where the node starts, which may be above the range.
+        let indent = utils::count_spaces_after_last_newline(source.text(), node.range().start);
         let res = doc
             .nest(indent as isize)
-            .pretty(self.config.max_width)
+            .pretty(self.config.render_width())
             .to_string();
         Ok((node.range(), res))
     }
+
+    /// Format a single expression in code, such as `1 + 2`, rather than a whole document.
+    ///
+    /// Returns [`Error::SyntaxError`] if the input has syntax errors, or is not exactly one
+    /// expression, e.g., when it has several statements or comments.
+    pub fn format_expr(self, expr_src: &str) -> Result<String, Error> {
+        // Parse it in a code block, so that the nodes get spans like in a detached source.
+        let source = Source::detached(format!("#{{\n{expr_src}\n}}"));
+        let root = source.root();
+        // The wrapper block must be the whole source, or the input closed it early.
+        let block = match root.children().as_slice() {
+            [hash, block] if hash.kind() == SyntaxKind::Hash && !root.erroneous() => {
+                block.cast::<CodeBlock>()
+            }
+            _ => None,
+        };
+        // Comments around the expression are placed outside the `Code` node.
+        let Some(block) = block.filter(|block| {
+            !block
+                .to_untyped()
+                .children()
+                .any(|child| child.kind().is_trivia() && child.kind() != SyntaxKind::Space)
+        }) else {
+            return Err(Error::SyntaxError);
+        };
+        let code = block.body().to_untyped();
+        let mut children = code
+            .children()
+            .filter(|child| child.kind() != SyntaxKind::Space);
+        let (Some(expr), None) = (
+            children.next().and_then(|child| child.cast()),
+            children.next(),
+        ) else {
+            return Err(Error::SyntaxError);
+        };
+
+        let attrs = AttrStore::new(root);
+        let printer = PrettyPrinter::new(self.config.clone(), &attrs)
+            .with_raw_formatters(self.raw_formatters.clone());
+        printer.push_mode(Mode::Code);
+        let res = printer
+            .convert_expr(expr)
+            .pretty(self.config.render_width())
+            .to_string();
+        Ok(res)
+    }
 }
 
 /// Get a Markup/Expr/Pattern node from source with minimal span that covering the given range.
@@ -60,7 +115,10 @@ fn get_node_cover_range_impl(
     let mode = match node.kind() {
         SyntaxKind::Markup => Mode::Markup,
         SyntaxKind::CodeBlock => Mode::Code,
-        SyntaxKind::Equation => Mode::Math,
+        SyntaxKind::Equation => match node.cast::<Equation>() {
+            Some(equation) if !equation.block() => Mode::MathInline,
+            _ => Mode::Math,
+        },
         _ => mode,
     };
     for child in node.children() {
@@ -75,3 +133,77 @@ fn get_node_cover_range_impl(
     .then(|| (node.span(), mode))
     // It returns span to avoid problems with borrowing.
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_range(content: &str, range: Range<usize>) -> (Range<usize>, String) {
+        let source = Source::detached(content);
+        Typstyle::default()
+            .format_source_range(&source, range)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_range_in_stmt() {
+        let content = "#{\nlet a  =  1\nlet b  =  2\n}\n";
+        let (range, res) = format_range(content, 13..14);
+        assert_eq!(&content[range], "1");
+        assert_eq!(res, "1");
+        let (range, res) = format_range(content, 3..14);
+        assert_eq!(&content[range], "let a  =  1");
+        assert_eq!(res, "let a = 1");
+    }
+
+    #[test]
+    fn test_range_across_stmts() {
+        let content = "#{\nlet a  =  1\nlet b  =  2\n}\n";
+        let (range, res) = format_range(content, 7..20);
+        assert_eq!(range, 1..28);
+        assert_eq!(res, "{\n  let a = 1\n  let b = 2\n}");
+    }
+
+    #[test]
+    fn test_range_in_nested_block() {
+        let content =
+            "#{\n  let a = {\n    let b = {\n      let c  =  (1,2)\n      c\n    }\n  }\n}\n";
+        let start = content.find("(1,2)").unwrap();
+        let end = content.find("      c\n").unwrap() + 7;
+        let (range, res) = format_range(content, start..end);
+        assert_eq!(&content[range], "{\n      let c  =  (1,2)\n      c\n    }");
+        assert_eq!(res, "{\n      let c = (1, 2)\n      c\n    }");
+    }
+
+    #[test]
+    fn test_range_embedded_in_markup() {
+        let content = "#(x)text\n";
+        let source = Source::detached(content);
+        let config = crate::Config::new().with_remove_redundant_parens(true);
+        let (range, res) = Typstyle::new(config)
+            .format_source_range(&source, 1..4)
+            .unwrap();
+        assert_eq!(&content[range], "(x)");
+        assert_eq!(res, "(x)");
+    }
+
+    #[test]
+    fn test_format_expr() {
+        let format_expr = |src: &str| Typstyle::default().format_expr(src);
+        assert_eq!(format_expr("1  +  2").unwrap(), "1 + 2");
+        assert_eq!(format_expr("  f( a,b )\n").unwrap(), "f(a, b)");
+        assert_eq!(format_expr("let  x  =  (1,2)").unwrap(), "let x = (1, 2)");
+        for src in [
+            "",
+            "1\n2",
+            "1 2",
+            "// c\n1",
+            "f(",
+            "}",
+            "1 } #{ 2",
+            "1 }\n= Heading\n#{ 2",
+        ] {
+            assert!(matches!(format_expr(src), Err(Error::SyntaxError)));
+        }
+    }
+}
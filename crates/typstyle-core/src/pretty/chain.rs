@@ -32,6 +32,11 @@ pub struct ChainStyle {
     pub no_break_single: bool,
     /// Add space before and after operators.
     pub space_around_op: bool,
+    /// Break lines after operators instead of before them. Ignored if the chain has comments.
+    pub break_after_op: bool,
+    /// Always break lines between operators if the chain has at least this many operators.
+    /// Disabled if it is 0.
+    pub break_threshold: usize,
 }
 
 impl<'a> ChainStylist<'a> {
@@ -140,13 +145,18 @@ impl<'a> ChainStylist<'a> {
     pub fn print_doc(self, sty: ChainStyle) -> ArenaDoc<'a> {
         let arena = &self.printer.arena;
 
-        let op_sep = if sty.space_around_op {
+        let use_simple_layout = self.chain_op_num == 1 && sty.no_break_single && !self.has_comment;
+        let force_break = sty.break_threshold > 0 && self.chain_op_num >= sty.break_threshold;
+
+        let op_sep = if force_break {
+            arena.hardline()
+        } else if sty.space_around_op {
             arena.line()
         } else {
             arena.line_()
         };
 
-        let use_simple_layout = self.chain_op_num == 1 && sty.no_break_single && !self.has_comment;
+        let break_after_op = sty.break_after_op && !self.has_comment;
 
         let mut docs = vec![];
         let mut has_break = false;
@@ -163,6 +173,23 @@ impl<'a> ChainStylist<'a> {
                     leading = false;
                     space_after = true;
                 }
+                ChainItem::Op(op) if break_after_op => {
+                    let op = if sty.space_around_op {
+                        arena.text(" ") + op
+                    } else {
+                        op
+                    };
+                    if let Some(last) = docs.last_mut() {
+                        *last += op;
+                    } else {
+                        docs.push(op);
+                    }
+                    if !use_simple_layout {
+                        docs.push(op_sep.clone());
+                    }
+                    leading = false;
+                    space_after = false;
+                }
                 ChainItem::Op(op) => {
                     if !(has_break && leading || use_simple_layout) {
                         docs.push(op_sep.clone());
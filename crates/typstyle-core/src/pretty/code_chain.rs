@@ -2,7 +2,7 @@ use itertools::Itertools;
 use pretty::DocAllocator;
 use typst_syntax::{ast::*, SyntaxKind, SyntaxNode};
 
-use crate::PrettyPrinter;
+use crate::{OperatorBreak, PrettyPrinter};
 
 use super::{
     chain::{iterate_deep_nodes, ChainStyle, ChainStylist},
@@ -41,7 +41,9 @@ impl<'a> PrettyPrinter<'a> {
                 has_comment = true;
             }
         }
-        if dot_num > 1 && call_num == 1 && !has_comment {
+        let force_break =
+            self.config.dot_chain_threshold > 0 && dot_num >= self.config.dot_chain_threshold;
+        if dot_num > 1 && call_num == 1 && !has_comment && !force_break {
             if let Some(res) = self.try_convert_dot_chain_plain(chain) {
                 return Some(res);
             }
@@ -113,6 +115,7 @@ impl<'a> PrettyPrinter<'a> {
             )
             .print_doc(ChainStyle {
                 no_break_single: true,
+                break_threshold: self.config.dot_chain_threshold,
                 ..Default::default()
             })
     }
@@ -139,6 +142,7 @@ impl<'a> PrettyPrinter<'a> {
             )
             .print_doc(ChainStyle {
                 space_around_op: true,
+                break_after_op: self.config.operator_break == OperatorBreak::After,
                 ..Default::default()
             })
     }
@@ -167,3 +171,73 @@ pub(super) fn resolve_binary_chain(binary: Binary<'_>) -> impl Iterator<Item = &
         None
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::format, Config, OperatorBreak};
+
+    #[test]
+    fn test_short_dot_chain() {
+        let content = "#{\n  a.b.c()\n  foo.bar().baz(1, 2)\n}\n#a.b.c()\n";
+        assert_eq!(format(content, Config::new()), content);
+
+        let content = "#{\n  foo.bar().baz(1, 2)\n}\n";
+        assert_eq!(
+            format(content, Config::new().with_width(20)),
+            "#{\n  foo\n    .bar()\n    .baz(1, 2)\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_dot_chain_in_markup() {
+        // A broken chain must be parenthesized, or the markup would end at the first linebreak.
+        let content = "#foo.bar().baz(1, 2).qux()\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_width(20)),
+            "#(\n  foo\n    .bar()\n    .baz(1, 2)\n    .qux()\n)\n"
+        );
+        // Field accesses without calls are never broken.
+        let content = "#aaaa.bbbb.cccc.dddd\n";
+        assert_eq!(format(content, Config::new().with_width(10)), content);
+    }
+
+    #[test]
+    fn test_dot_chain_threshold() {
+        let content = "#{\n  a.b.c.d\n  x.y\n  f.g.h(1)\n}\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_dot_chain_threshold(4)),
+            content
+        );
+        assert_eq!(
+            format(content, Config::new().with_dot_chain_threshold(2)),
+            "#{\n  a\n    .b\n    .c\n    .d\n  x.y\n  f\n    .g\n    .h(1)\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_operator_break() {
+        let content = "#let x = (aaaaaaaaaa + bbbbbbbbbb * 2 + cccccccccc)\n";
+        assert_eq!(
+            format(content, Config::new().with_width(30)),
+            "#let x = (\n  aaaaaaaaaa\n    + bbbbbbbbbb * 2\n    + cccccccccc\n)\n"
+        );
+        assert_eq!(
+            format(
+                content,
+                Config::new()
+                    .with_width(30)
+                    .with_operator_break(OperatorBreak::After)
+            ),
+            "#let x = (\n  aaaaaaaaaa +\n    bbbbbbbbbb * 2 +\n    cccccccccc\n)\n"
+        );
+        assert_eq!(
+            format(
+                content,
+                Config::new().with_operator_break(OperatorBreak::After)
+            ),
+            content
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use pretty::DocAllocator;
-use typst_syntax::{ast::*, SyntaxKind};
+use typst_syntax::{ast::*, SyntaxKind, SyntaxNode};
 
 use crate::ext::BoolExt;
 
@@ -33,7 +33,14 @@ impl<'a> PrettyPrinter<'a> {
                 FlowItem::tight_spaced(self.arena.text(":"))
             } else if let Some(expr) = child.cast() {
                 // key, expr
-                FlowItem::spaced_before(self.convert_expr(expr), seen_key.replace(true))
+                let doc = match expr {
+                    // Parentheses around a key are never redundant, e.g., `((x): 1)`.
+                    Expr::Parenthesized(paren) if !seen_key => self
+                        .check_disabled(paren.to_untyped())
+                        .unwrap_or_else(|| self.convert_parenthesized(paren)),
+                    _ => self.convert_expr(expr),
+                };
+                FlowItem::spaced_before(doc, seen_key.replace(true))
             } else {
                 FlowItem::none()
             }
@@ -128,9 +135,14 @@ impl<'a> PrettyPrinter<'a> {
     }
 
     pub(super) fn convert_let_binding(&'a self, let_binding: LetBinding<'a>) -> ArenaDoc<'a> {
+        let mut seen_eq = false;
         self.convert_flow_like(let_binding.to_untyped(), |child| {
             if child.kind() == SyntaxKind::Eq {
+                seen_eq = true;
                 FlowItem::spaced(self.arena.text("="))
+            } else if let Some(expr) = child.cast().filter(|_| seen_eq) {
+                // init
+                FlowItem::spaced(self.convert_expr(expr))
             } else if let Some(pattern) = child.cast() {
                 // Must try pattern before expr
                 FlowItem::spaced(self.convert_pattern(pattern))
@@ -164,11 +176,27 @@ impl<'a> PrettyPrinter<'a> {
     }
 
     pub(super) fn convert_conditional(&'a self, conditional: Conditional<'a>) -> ArenaDoc<'a> {
-        self.convert_expr_flow(conditional.to_untyped())
+        self.convert_condition_flow(conditional.to_untyped())
     }
 
     pub(super) fn convert_while_loop(&'a self, while_loop: WhileLoop<'a>) -> ArenaDoc<'a> {
-        self.convert_expr_flow(while_loop.to_untyped())
+        self.convert_condition_flow(while_loop.to_untyped())
+    }
+
+    /// Convert a node led by a condition, which is put in parentheses if it breaks.
+    fn convert_condition_flow(&'a self, node: &'a SyntaxNode) -> ArenaDoc<'a> {
+        let mut seen_condition = false;
+        self.convert_flow_like(node, |child| {
+            let Some(expr) = child.cast() else {
+                return FlowItem::none();
+            };
+            if seen_condition.replace(true) || is_atomic_condition(expr) {
+                // bodies, or conditions that never break
+                FlowItem::spaced(self.convert_expr(expr))
+            } else {
+                FlowItem::spaced(self.convert_expr_with_optional_paren(expr))
+            }
+        })
     }
 
     pub(super) fn convert_for_loop(&'a self, for_loop: ForLoop<'a>) -> ArenaDoc<'a> {
@@ -237,3 +265,154 @@ impl<'a> PrettyPrinter<'a> {
         })
     }
 }
+
+/// Checks if a condition is a single atom, optionally under a unary operator, which never breaks.
+fn is_atomic_condition(expr: Expr<'_>) -> bool {
+    let expr = match expr {
+        Expr::Unary(unary) => unary.expr(),
+        expr => expr,
+    };
+    matches!(
+        expr,
+        Expr::Ident(_)
+            | Expr::None(_)
+            | Expr::Auto(_)
+            | Expr::Bool(_)
+            | Expr::Int(_)
+            | Expr::Float(_)
+            | Expr::Numeric(_)
+            | Expr::Str(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::format, Config, QuoteStyle};
+
+    #[test]
+    fn test_else_if_chain() {
+        let content = "#if a {\n  1\n} else if b {\n  2\n} else {\n  3\n}\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(
+                "#if a {\n1 } else if b {\n2 }  else  {\n  3 }\n",
+                Config::new()
+            ),
+            content
+        );
+
+        let content = "#{\n  if a {\n    1\n  } else if b {\n    2\n  } else {\n    3\n  }\n}\n";
+        assert_eq!(format(content, Config::new()), content);
+    }
+
+    #[test]
+    fn test_let_closure_body() {
+        let content = "#let f(x) = {\n  let y = x + 1\n  y * 2\n}\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(format(content, Config::new().with_width(20)), content);
+        assert_eq!(
+            format(
+                "#let f(x)  =  {\n    let y = x + 1\n    y * 2 }\n",
+                Config::new()
+            ),
+            content
+        );
+    }
+
+    #[test]
+    fn test_sink_params() {
+        for content in [
+            "#let f = (..args) => args\n",
+            "#let f = (a, ..rest) => none\n",
+            "#let f(..args) = args\n",
+        ] {
+            assert_eq!(format(content, Config::new()), content);
+        }
+        assert_eq!(
+            format("#let f = ( ..args ) => args\n", Config::new()),
+            "#let f = (..args) => args\n"
+        );
+        assert_eq!(
+            format("#let f = (a , ..rest) => none\n", Config::new()),
+            "#let f = (a, ..rest) => none\n"
+        );
+    }
+
+    #[test]
+    fn test_show_rule() {
+        let content = "#show: template.with(title: [A])\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format("#show:template\n", Config::new()),
+            "#show: template\n"
+        );
+        assert_eq!(
+            format("#show:  template\n", Config::new()),
+            "#show: template\n"
+        );
+
+        let content = "#show heading: it => {\n  set text(red)\n  it\n}\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(
+                "#show heading:it => {\n    set text(red)\n    it }\n",
+                Config::new()
+            ),
+            content
+        );
+    }
+
+    #[test]
+    fn test_include_path() {
+        assert_eq!(
+            format("#include  \"x.typ\"\n", Config::new()),
+            "#include \"x.typ\"\n"
+        );
+
+        let config = Config::new().with_quote_style(QuoteStyle::Normalize);
+        assert_eq!(
+            format("#include \"\\u{61}.typ\"\n", config.clone()),
+            "#include \"a.typ\"\n"
+        );
+        assert_eq!(
+            format("#import \"\\u{61}.typ\"\n", config),
+            "#import \"a.typ\"\n"
+        );
+    }
+
+    #[test]
+    fn test_contextual_block() {
+        let content = "#context {\n  let x = here().position()\n  x.y\n}\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(
+                "#context   {\n    let x = here().position()\n    x.y }\n",
+                Config::new()
+            ),
+            content
+        );
+        let content = "#{\n  context {\n    let x = here().position()\n    x.y\n  }\n}\n";
+        assert_eq!(format(content, Config::new()), content);
+    }
+
+    #[test]
+    fn test_loop_bodies() {
+        let content = "#for x in xs {\n  let y = x * 2\n  [#y]\n}\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format("#for x in xs {\nlet y = x * 2\n    [#y] }\n", Config::new()),
+            content
+        );
+        let content = "#{\n  while i < 3 {\n    i += 1\n    f(i)\n  }\n}\n";
+        assert_eq!(format(content, Config::new()), content);
+
+        // Bodies with a single expression can stay inline.
+        for content in [
+            "#for x in xs [#x]\n",
+            "#for x in xs { x }\n",
+            "#{\n  while i < 3 { i += 1 }\n}\n",
+        ] {
+            assert_eq!(format(content, Config::new()), content);
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use typst_syntax::{ast::*, SyntaxKind};
+use itertools::Itertools;
+use rustc_hash::FxHashSet;
+use typst_syntax::{ast::*, SyntaxKind, SyntaxNode};
+
+use crate::{StatementSeparator, TrailingComma};
 
 use super::{
     list::{ListStyle, ListStylist},
@@ -28,7 +32,9 @@ impl<'a> PrettyPrinter<'a> {
             }
         }
 
-        let can_fold = code_block.body().exprs().count() <= 1
+        // Statements can only be put in one line if they are separated by semicolons.
+        let can_fold = (code_block.body().exprs().count() <= 1
+            || self.config.code_statement_separator == StatementSeparator::Semicolon)
             && !has_comment_children(code_block.to_untyped());
         ListStylist::new(self)
             .disallow_front_comment()
@@ -40,9 +46,12 @@ impl<'a> PrettyPrinter<'a> {
             .keep_linebreak(self.config.blank_lines_upper_bound)
             .process_iterable(nodes.into_iter(), |expr| self.convert_expr(expr))
             .print_doc(ListStyle {
-                separator: "",
+                separator: ";",
+                flat_separator_only: true,
                 delim: ("{", "}"),
                 add_delim_space: true,
+                trailing_sep: TrailingComma::Never,
+                align_comments: self.config.align_comments,
                 ..Default::default()
             })
     }
@@ -80,11 +89,20 @@ impl<'a> PrettyPrinter<'a> {
     pub(super) fn convert_array(&'a self, array: Array<'a>) -> ArenaDoc<'a> {
         let _g = self.with_mode(Mode::CodeCont);
 
+        // The comma of an array with one item is required, so it does not keep the array broken.
+        let fold_style = if array.items().nth(1).is_some()
+            && self.has_magic_trailing_comma(array.to_untyped())
+        {
+            FoldStyle::Never
+        } else {
+            self.get_fill_fold_style(array)
+        };
         ListStylist::new(self)
-            .with_fold_style(self.get_fold_style(array))
+            .with_fold_style(fold_style)
             .process_list(array.to_untyped(), |node| self.convert_array_item(node))
             .print_doc(ListStyle {
                 add_trailing_sep_single: true,
+                trailing_sep: self.config.trailing_comma,
                 ..Default::default()
             })
     }
@@ -94,11 +112,32 @@ impl<'a> PrettyPrinter<'a> {
 
         let all_spread = dict.items().all(|item| matches!(item, DictItem::Spread(_)));
 
+        let children = dict.to_untyped().children();
+        // Entries are never reordered across comments or spreads.
+        let children: Vec<&SyntaxNode> =
+            if self.config.sort_dict_keys && !has_comment_children(dict.to_untyped()) {
+                let mut items = children
+                    .filter(|child| child.is::<DictItem>())
+                    .collect_vec();
+                for run in items.split_mut(|child| child.kind() == SyntaxKind::Spread) {
+                    run.sort_by_cached_key(|child| dict_key_name(child));
+                }
+                items
+            } else {
+                children.collect()
+            };
+
+        let fold_style = if self.has_magic_trailing_comma(dict.to_untyped()) {
+            FoldStyle::Never
+        } else {
+            self.get_fold_style(dict)
+        };
         ListStylist::new(self)
-            .with_fold_style(self.get_fold_style(dict))
-            .process_list(dict.to_untyped(), |node| self.convert_dict_item(node))
+            .with_fold_style(fold_style)
+            .process_iterable(children.into_iter(), |node| self.convert_dict_item(node))
             .print_doc(ListStyle {
                 delim: (if all_spread { "(:" } else { "(" }, ")"),
+                trailing_sep: self.config.trailing_comma,
                 ..Default::default()
             })
     }
@@ -143,15 +182,39 @@ impl<'a> PrettyPrinter<'a> {
             .always_fold_if(|| is_single_simple)
             .print_doc(ListStyle {
                 omit_delim_single: is_single_simple,
+                // A trailing comma would require the parens back, as in `(x,) => x`.
+                trailing_sep: if is_single_simple {
+                    TrailingComma::Multiline
+                } else {
+                    self.config.trailing_comma
+                },
                 ..Default::default()
             })
     }
 
     pub(super) fn convert_import_items(&'a self, import_items: ImportItems<'a>) -> ArenaDoc<'a> {
+        let children = import_items.to_untyped().children();
+        // Items are never reordered or removed across comments.
+        let children: Vec<&SyntaxNode> = if (self.config.sort_imports || self.config.dedup_imports)
+            && !has_comment_children(import_items.to_untyped())
+        {
+            let mut items = children.filter(|child| is_import_item(child)).collect_vec();
+            if self.config.dedup_imports {
+                let mut seen = FxHashSet::default();
+                items.retain(|child| seen.insert(import_item_key(child)));
+            }
+            if self.config.sort_imports {
+                items.sort_by_cached_key(|child| import_item_name(child));
+            }
+            items
+        } else {
+            children.collect()
+        };
+
         // Note that `ImportItem` does not implement `AstNode`.
         ListStylist::new(self)
             .with_fold_style(self.get_fold_style(import_items))
-            .process_list_impl(import_items.to_untyped(), |child| match child.kind() {
+            .process_iterable_impl(children.into_iter(), |child| match child.kind() {
                 SyntaxKind::RenamedImportItem => child
                     .cast()
                     .map(|item| self.convert_import_item_renamed(item)),
@@ -167,3 +230,304 @@ impl<'a> PrettyPrinter<'a> {
             })
     }
 }
+
+fn is_import_item(node: &SyntaxNode) -> bool {
+    matches!(
+        node.kind(),
+        SyntaxKind::ImportItemPath | SyntaxKind::RenamedImportItem
+    )
+}
+
+/// Get the original name of an import item, i.e., the path before `as` if renamed.
+fn import_item_name(node: &SyntaxNode) -> String {
+    let path = if node.kind() == SyntaxKind::RenamedImportItem {
+        node.children()
+            .find(|child| child.kind() == SyntaxKind::ImportItemPath)
+            .unwrap_or(node)
+    } else {
+        node
+    };
+    path.clone().into_text().to_string()
+}
+
+/// Get the key of a dictionary entry, i.e., the name or the string value.
+fn dict_key_name(node: &SyntaxNode) -> String {
+    match node.cast::<DictItem>() {
+        Some(DictItem::Named(named)) => named.name().get().to_string(),
+        Some(DictItem::Keyed(keyed)) => match keyed.key() {
+            Expr::Str(key) => key.get().to_string(),
+            key => key.to_untyped().clone().into_text().to_string(),
+        },
+        _ => String::new(),
+    }
+}
+
+/// Get the text of an import item with whitespace normalized, so that items importing the same
+/// name under the same alias have the same key.
+fn import_item_key(node: &SyntaxNode) -> String {
+    node.clone().into_text().split_whitespace().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::format, Config, StatementSeparator, TrailingComma, Typstyle};
+
+    #[test]
+    fn test_sort_imports() {
+        let content = "#import \"x.typ\": gamma, alpha.b as a, beta\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_sort_imports(true)),
+            "#import \"x.typ\": alpha.b as a, beta, gamma\n"
+        );
+        let content = "#import \"x.typ\": *\n";
+        assert_eq!(
+            format(content, Config::new().with_sort_imports(true)),
+            content
+        );
+    }
+
+    #[test]
+    fn test_dedup_imports() {
+        let content = "#import \"x.typ\": a, b, a, c as d, c as e, c as d, b.f\n";
+        assert_eq!(format(content, Config::new()), content);
+        let dedup = Config::new().with_dedup_imports(true);
+        assert_eq!(
+            format(content, dedup.clone()),
+            "#import \"x.typ\": a, b, c as d, c as e, b.f\n"
+        );
+        assert_eq!(
+            format(content, dedup.with_sort_imports(true)),
+            "#import \"x.typ\": a, b, b.f, c as d, c as e\n"
+        );
+        let content = "#import \"x.typ\": (\n  a, // a\n  a,\n)\n";
+        assert_eq!(
+            format(content, Config::new().with_dedup_imports(true)),
+            format(content, Config::new())
+        );
+    }
+
+    #[test]
+    fn test_sort_dict_keys() {
+        let content = "#let d = (b: 1, a: 2, ..base, \"z\": 3, c: 4)\n";
+        assert_eq!(format(content, Config::new()), content);
+        let config = Config::new().with_sort_dict_keys(true);
+        assert_eq!(
+            format(content, config.clone()),
+            "#let d = (a: 2, b: 1, ..base, c: 4, \"z\": 3)\n"
+        );
+        let content = "#let d = (\n  b: 1, // b\n  a: 2,\n)\n";
+        assert_eq!(format(content, config), format(content, Config::new()));
+    }
+
+    #[test]
+    fn test_import_overflow() {
+        let content = "#import \"mod.typ\": alpha, beta, gamma\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_width(30)),
+            "#import \"mod.typ\": (\n  alpha,\n  beta,\n  gamma,\n)\n"
+        );
+        assert_eq!(
+            format("#import \"mod.typ\": (alpha, beta)\n", Config::new()),
+            "#import \"mod.typ\": alpha, beta\n"
+        );
+    }
+
+    #[test]
+    fn test_fill_lists() {
+        let items = (1..=20)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let filled =
+            "(\n  1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,\n  13, 14, 15, 16, 17, 18, 19, 20,\n)\n";
+        let config = Config::new().with_width(40).with_fill_lists(true);
+        assert_eq!(
+            format(&format!("#let x = ({items})\n"), config.clone()),
+            format!("#let x = {filled}")
+        );
+        assert_eq!(
+            format(&format!("#f({items})\n"), config.clone()),
+            format!("#f{filled}")
+        );
+        let content = "#let x = (1, 2, 3)\n";
+        assert_eq!(format(content, config.clone()), content);
+        // Filled lists are broken, which must not keep them from being filled again.
+        let once = format(&format!("#let a = ({items})\n"), config.clone());
+        assert_eq!(format(&once, config), once);
+        assert_eq!(
+            format(&format!("#f({items})\n"), Config::new().with_width(40)),
+            format!("#f(\n  {},\n)\n", items.replace(", ", ",\n  "))
+        );
+    }
+
+    #[test]
+    fn test_trailing_comma() {
+        let always = Config::new().with_trailing_comma(TrailingComma::Always);
+        let never = Config::new()
+            .with_width(20)
+            .with_trailing_comma(TrailingComma::Never);
+        assert_eq!(
+            format("#let x = (1, 2)\n", always.clone()),
+            "#let x = (1, 2,)\n"
+        );
+        assert_eq!(
+            format("#let x = (a: 1)\n", always.clone()),
+            "#let x = (a: 1,)\n"
+        );
+        assert_eq!(format("#f(a, b)\n", always), "#f(a, b,)\n");
+        let content = "#let x = (aaaaaaaaaa, bbbbbbbbbb)\n";
+        assert_eq!(
+            format(content, Config::new().with_width(20)),
+            "#let x = (\n  aaaaaaaaaa,\n  bbbbbbbbbb,\n)\n"
+        );
+        assert_eq!(
+            format(content, never.clone()),
+            "#let x = (\n  aaaaaaaaaa,\n  bbbbbbbbbb\n)\n"
+        );
+        assert_eq!(format("#let x = (1,)\n", never), "#let x = (1,)\n");
+    }
+
+    #[test]
+    fn test_params_overflow() {
+        let content = "#let f(aaaa, bbbb, cccc) = none\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_width(20)),
+            "#let f(\n  aaaa,\n  bbbb,\n  cccc,\n) = none\n"
+        );
+        assert_eq!(
+            format(
+                content,
+                Config::new()
+                    .with_width(20)
+                    .with_trailing_comma(TrailingComma::Never)
+            ),
+            "#let f(\n  aaaa,\n  bbbb,\n  cccc\n) = none\n"
+        );
+        let always = Config::new().with_trailing_comma(TrailingComma::Always);
+        assert_eq!(
+            format("#let f(a, b) = a\n", always.clone()),
+            "#let f(a, b,) = a\n"
+        );
+        assert_eq!(format("#let f = x => x\n", always), "#let f = x => x\n");
+    }
+
+    #[test]
+    fn test_single_element_array() {
+        let content = "#let x = (1,)\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_width(0)),
+            "#let x = (\n  1,\n)\n"
+        );
+        assert_eq!(format("#let x = ( 1 , )\n", Config::new()), content);
+        assert_eq!(format("#f((1,))\n", Config::new()), "#f((1,))\n");
+        assert_eq!(format("#let x = ((1,))\n", Config::new()), content);
+    }
+
+    #[test]
+    fn test_align_comments() {
+        let content = "#{\n  let a = 1 // a\n  let bcd = 22 // b\n  f(x)\n  let e = 3 // e\n  let ff = 4 // f\n\n  g() // g\n}\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_align_comments(true)),
+            "#{\n  let a = 1    // a\n  let bcd = 22 // b\n  f(x)\n  let e = 3  // e\n  let ff = 4 // f\n\n  g() // g\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_code_statement_separator() {
+        let content = "#{ let x = 1; x }\n";
+        let broken = "#{\n  let x = 1\n  x\n}\n";
+        assert_eq!(format(content, Config::new()), broken);
+
+        let config = Config::new().with_code_statement_separator(StatementSeparator::Semicolon);
+        assert_eq!(format(content, config.clone()), content);
+        assert_eq!(format(content, config.clone().with_width(10)), broken);
+        assert_eq!(format(broken, config), broken);
+    }
+
+    #[test]
+    fn test_single_statement_code_block() {
+        let content = "#{let x = 1}\n";
+        let flat = "#{ let x = 1 }\n";
+        let broken = "#{\n  let x = 1\n}\n";
+        assert_eq!(format(content, Config::new()), flat);
+        assert_eq!(format(content, Config::new().with_width(14)), flat);
+        assert_eq!(format(content, Config::new().with_width(13)), broken);
+        assert_eq!(format(content, Config::new().with_width(4)), broken);
+
+        // Code blocks broken by the author, or with comments, are not put in one line.
+        assert_eq!(format(broken, Config::new()), broken);
+        assert_eq!(
+            format("#{ let x = 1 /* one */ }\n", Config::new()),
+            "#{\n  let x = 1 /* one */\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_expanded_literals() {
+        // A linebreak after the opening paren keeps the literal expanded even if it fits.
+        for content in [
+            "#let x = (\n  1,\n  2,\n)\n",
+            "#let d = (\n  a: 1,\n  b: 2,\n)\n",
+        ] {
+            assert_eq!(format(content, Config::new()), content);
+        }
+        assert_eq!(
+            format("#let x = (1, 2,\n  3)\n", Config::new()),
+            "#let x = (1, 2, 3)\n"
+        );
+    }
+
+    #[test]
+    fn test_magic_trailing_comma() {
+        let config = Config::new().with_magic_trailing_comma(true);
+        for (content, expected) in [
+            ("#let x = (1, 2,)\n", "#let x = (\n  1,\n  2,\n)\n"),
+            ("#let d = (a: 1,)\n", "#let d = (\n  a: 1,\n)\n"),
+            ("#f(a, b,)\n", "#f(\n  a,\n  b,\n)\n"),
+        ] {
+            assert_eq!(format(content, config.clone()), expected);
+            assert_eq!(format(expected, config.clone()), expected);
+            // Without the trailing comma, the list is put in one line again.
+            let content = content.replace(",)", ")");
+            assert_eq!(format(&content, config.clone()), content);
+        }
+
+        let content = "#let x = (1,)\n";
+        assert_eq!(format(content, config.clone()), content);
+        assert_eq!(
+            format("#let x = (1, 2,)\n", Config::new()),
+            "#let x = (1, 2)\n"
+        );
+        assert_eq!(
+            format(
+                "#let x = (1, 2,)\n",
+                config.with_trailing_comma(TrailingComma::Always)
+            ),
+            "#let x = (1, 2,)\n"
+        );
+    }
+
+    #[test]
+    fn test_deeply_nested_array() {
+        fn nested(depth: usize) -> String {
+            if depth == 0 {
+                return "x".to_string();
+            }
+            let item = nested(depth - 1);
+            format!("({item}, {item}, {item})")
+        }
+
+        // Each level is converted once, so this stays fast. Inner levels that fit are kept flat.
+        let content = format!("#let x = {}\n", nested(6));
+        let result = Typstyle::new(Config::new())
+            .format_content_checked(content)
+            .unwrap();
+        assert!(result.lines().all(|line| line.len() <= 80));
+        assert!(result.contains("\n        ((x, x, x), (x, x, x), (x, x, x)),\n"));
+    }
+}
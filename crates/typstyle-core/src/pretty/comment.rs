@@ -1,21 +1,39 @@
 use pretty::{Arena, DocAllocator};
 use typst_syntax::{SyntaxKind, SyntaxNode};
 
-use super::{ArenaDoc, PrettyPrinter};
+use super::{Annotation, ArenaDoc, PrettyPrinter};
 
 impl<'a> PrettyPrinter<'a> {
     pub(super) fn convert_comment(&'a self, node: &'a SyntaxNode) -> ArenaDoc<'a> {
+        if self.config.line_comment_space
+            && node.kind() == SyntaxKind::LineComment
+            && !self.attr_store.is_format_disabled(node)
+        {
+            if let Some(text) = add_line_comment_space(node.text()) {
+                return self.arena.text(text);
+            }
+        }
         comment(&self.arena, node)
     }
 }
 
+/// Insert a space after `//` if there is none. Returns `None` if the comment is kept as is,
+/// including empty comments and those starting with `///` or `//!`.
+fn add_line_comment_space(text: &str) -> Option<String> {
+    let body = text.strip_prefix("//")?;
+    if body.is_empty() || body.starts_with(['/', '!']) || body.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(format!("// {body}"))
+}
+
 enum CommentStyle {
     Plain,
     Bullet,
 }
 
 /// Convert either line comment or block comment.
-pub fn comment<'a>(arena: &'a Arena<'a>, node: &'a SyntaxNode) -> ArenaDoc<'a> {
+pub fn comment<'a>(arena: &'a Arena<'a, Annotation>, node: &'a SyntaxNode) -> ArenaDoc<'a> {
     if node.kind() == SyntaxKind::LineComment {
         line_comment(arena, node)
     } else if node.kind() == SyntaxKind::BlockComment {
@@ -25,12 +43,12 @@ pub fn comment<'a>(arena: &'a Arena<'a>, node: &'a SyntaxNode) -> ArenaDoc<'a> {
     }
 }
 
-pub fn line_comment<'a>(arena: &'a Arena<'a>, node: &'a SyntaxNode) -> ArenaDoc<'a> {
+pub fn line_comment<'a>(arena: &'a Arena<'a, Annotation>, node: &'a SyntaxNode) -> ArenaDoc<'a> {
     arena.text(node.text().as_str())
 }
 
 /// It does not add a hardline to the doc.
-pub fn block_comment<'a>(arena: &'a Arena<'a>, node: &'a SyntaxNode) -> ArenaDoc<'a> {
+pub fn block_comment<'a>(arena: &'a Arena<'a, Annotation>, node: &'a SyntaxNode) -> ArenaDoc<'a> {
     // Calculate the number of leading spaces except the first line.
     let line_num = node.text().lines().count();
     if line_num == 0 {
@@ -67,7 +85,7 @@ fn get_follow_leading(text: &str) -> Option<usize> {
 }
 
 /// For general cases. All lines need to be indented together.
-fn align_multiline<'a>(arena: &'a Arena<'a>, text: &'a str) -> ArenaDoc<'a> {
+fn align_multiline<'a>(arena: &'a Arena<'a, Annotation>, text: &'a str) -> ArenaDoc<'a> {
     let leading = get_follow_leading(text).unwrap();
     let mut doc = arena.nil();
     for (i, line) in text.lines().enumerate() {
@@ -84,7 +102,7 @@ fn align_multiline<'a>(arena: &'a Arena<'a>, text: &'a str) -> ArenaDoc<'a> {
 }
 
 /// For special cases. All lines can be indented independently.
-fn align_multiline_simple<'a>(arena: &'a Arena<'a>, text: &'a str) -> ArenaDoc<'a> {
+fn align_multiline_simple<'a>(arena: &'a Arena<'a, Annotation>, text: &'a str) -> ArenaDoc<'a> {
     let mut doc = arena.nil();
     for (i, line) in text.lines().enumerate() {
         if i > 0 {
@@ -99,7 +117,40 @@ fn align_multiline_simple<'a>(arena: &'a Arena<'a>, text: &'a str) -> ArenaDoc<'
 mod tests {
     use pretty::{Arena, DocAllocator};
 
-    use crate::pretty::comment::{align_multiline, align_multiline_simple, get_follow_leading};
+    use crate::{
+        pretty::comment::{align_multiline, align_multiline_simple, get_follow_leading},
+        tests::format,
+        Config,
+    };
+
+    #[test]
+    fn test_line_comment_space() {
+        let content = "//foo\n// bar\n///doc\n//!x\n////\n#{\n  let x = 1 //y\n}\n/*a*/\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_line_comment_space(true)),
+            "// foo\n// bar\n///doc\n//!x\n////\n#{\n  let x = 1 // y\n}\n/*a*/\n"
+        );
+
+        let content = "// @typstyle off\n#{\n  let x = 1 //y\n}\n";
+        assert_eq!(
+            format(content, Config::new().with_line_comment_space(true)),
+            content
+        );
+    }
+
+    #[test]
+    fn test_reindent_block_comment() {
+        // Interior lines follow the comment when it moves, and bullet-style lines are aligned.
+        let content = "#{\n      /* a\n         b */\n  /* c\n     * d */\n  x\n}\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "#{\n  /* a\n  b */\n  /* c\n   * d */\n  x\n}\n"
+        );
+
+        let content = "// @typstyle off\n#{\n        /* a\n           b */\n  let x = 1\n}\n";
+        assert_eq!(format(content, Config::new()), content);
+    }
 
     #[test]
     fn test_align() {
@@ -1,5 +1,6 @@
+use itertools::Itertools;
 use pretty::DocAllocator;
-use typst_syntax::{ast::*, SyntaxKind};
+use typst_syntax::{ast::*, SyntaxKind, SyntaxNode};
 
 use super::list::{ListStyle, ListStylist};
 use super::mode::Mode;
@@ -7,16 +8,19 @@ use super::plain::PlainStylist;
 use super::style::FoldStyle;
 use super::util::is_only_one_and;
 use super::PrettyPrinter;
+use crate::TrailingComma;
 
 use super::{
     table,
-    util::{get_parenthesized_args_untyped, has_parenthesized_args},
+    util::{get_parenthesized_args_untyped, has_parenthesized_args, is_2d_arg, is_separated_list},
     ArenaDoc,
 };
 
 impl<'a> PrettyPrinter<'a> {
     pub(super) fn convert_func_call(&'a self, func_call: FuncCall<'a>) -> ArenaDoc<'a> {
-        if func_call.callee().to_untyped().kind() == SyntaxKind::FieldAccess {
+        if func_call.callee().to_untyped().kind() == SyntaxKind::FieldAccess
+            && !table::is_table(func_call, &self.config.extra_table_functions)
+        {
             if let Some(res) = self.try_convert_dot_chain(func_call.to_untyped()) {
                 return res;
             }
@@ -31,14 +35,16 @@ impl<'a> PrettyPrinter<'a> {
 
     fn convert_func_call_args(&'a self, func_call: FuncCall<'a>, args: Args<'a>) -> ArenaDoc<'a> {
         if self.current_mode().is_math() {
-            return self.format_disabled(args.to_untyped());
+            return self.convert_math_args(args);
         }
         let _g = self.with_mode(Mode::CodeCont);
 
         let mut doc = self.arena.nil();
         let has_parenthesized_args = has_parenthesized_args(args);
-        if table::is_table(func_call) {
-            if let Some(cols) = table::is_formatable_table(func_call) {
+        let extra_tables = &self.config.extra_table_functions;
+        // Tables are always reflowed into multiple lines, which is not wanted in compact mode.
+        if !self.config.compact && table::is_table(func_call, extra_tables) {
+            if let Some(cols) = table::is_formatable_table(func_call, extra_tables) {
                 doc += self.convert_table(func_call, cols);
             } else if has_parenthesized_args {
                 doc += self.convert_parenthesized_args_as_is(args);
@@ -62,7 +68,7 @@ impl<'a> PrettyPrinter<'a> {
     pub(super) fn convert_parenthesized_args(&'a self, args: Args<'a>) -> ArenaDoc<'a> {
         let _g = self.with_mode(Mode::CodeCont);
 
-        let mut fold_style = self.get_fold_style(args);
+        let mut fold_style = self.get_fill_fold_style(args);
 
         let children = || {
             args.to_untyped()
@@ -90,6 +96,9 @@ impl<'a> PrettyPrinter<'a> {
             };
             true
         });
+        if self.has_magic_trailing_comma(args.to_untyped()) {
+            fold_style = FoldStyle::Never;
+        }
 
         ListStylist::new(self)
             .keep_linebreak(self.config.blank_lines_upper_bound)
@@ -99,6 +108,7 @@ impl<'a> PrettyPrinter<'a> {
                 child.cast().map(|arg| self.convert_arg(arg))
             })
             .print_doc(ListStyle {
+                trailing_sep: self.config.trailing_comma,
                 ..Default::default()
             })
     }
@@ -131,6 +141,119 @@ impl<'a> PrettyPrinter<'a> {
             .concat(args.map(|arg| self.convert_content_block(arg)))
     }
 
+    /// Convert the args of a function call in math, like `vec(1, 2)`. They are kept as they are
+    /// if they fit in a line, or put one per line otherwise if `break_math_args` is set. Args
+    /// with rows like `mat(1, 2; 3, 4)` are broken into one row per line.
+    ///
+    /// Args with empty items or a trailing separator, like `f(a,,)`, are always kept as they are.
+    fn convert_math_args(&'a self, args: Args<'a>) -> ArenaDoc<'a> {
+        let as_is = self.format_disabled(args.to_untyped());
+        if self.current_mode() == Mode::MathInline
+            || self.attr_store.is_unformattable(args.to_untyped())
+        {
+            return as_is;
+        }
+        let broken = if is_2d_arg(args) {
+            match self.convert_math_rows(args) {
+                Some(rows) => rows,
+                None => return as_is,
+            }
+        } else if self.config.break_math_args
+            && is_separated_list(args.to_untyped(), |kind| kind == SyntaxKind::Comma)
+        {
+            ListStylist::new(self)
+                .with_fold_style(FoldStyle::Never)
+                .process_list_impl(args.to_untyped(), |child| self.convert_math_arg(child))
+                .print_doc(ListStyle {
+                    trailing_sep: TrailingComma::Never,
+                    ..Default::default()
+                })
+        } else {
+            return as_is;
+        };
+        if self.attr_store.is_multiline(args.to_untyped()) {
+            broken
+        } else {
+            broken.flat_alt(as_is).group()
+        }
+    }
+
+    /// Convert math args with rows, putting each row and named arg on its own line and aligning
+    /// the cells of each column. Args and separators are kept in their source order. Returns
+    /// `None` if there are args other than named ones and rows, or empty args.
+    fn convert_math_rows(&'a self, args: Args<'a>) -> Option<ArenaDoc<'a>> {
+        let is_row_sep = |kind| matches!(kind, SyntaxKind::Comma | SyntaxKind::Semicolon);
+        if !is_separated_list(args.to_untyped(), is_row_sep) {
+            return None;
+        }
+        let mut rows = vec![];
+        for child in args.to_untyped().children() {
+            match child.kind() {
+                SyntaxKind::Array if is_separated_list(child, |kind| kind == SyntaxKind::Comma) => {
+                    rows.push(
+                        child
+                            .children()
+                            .filter_map(|cell| self.convert_math_arg(cell))
+                            .collect_vec(),
+                    )
+                }
+                SyntaxKind::Named | SyntaxKind::Space | SyntaxKind::LeftParen => {}
+                SyntaxKind::RightParen => {}
+                kind if is_row_sep(kind) => {}
+                _ => return None,
+            }
+        }
+
+        let paddings = table::get_cell_paddings(&rows, self.config.render_width());
+        let mut rows = rows.into_iter().enumerate();
+        let mut doc = self.arena.nil();
+        for child in args.to_untyped().children() {
+            match child.kind() {
+                SyntaxKind::Named => {
+                    doc += self.arena.hardline() + self.convert_named(child.cast()?);
+                }
+                SyntaxKind::Array => {
+                    let (i, row) = rows.next()?;
+                    let row_paddings = paddings.as_ref().map(|paddings| &paddings[i]);
+                    let last = row.len().saturating_sub(1);
+                    doc += self.arena.hardline();
+                    for (j, cell) in row.into_iter().enumerate() {
+                        doc += cell;
+                        if j < last {
+                            let padding = row_paddings.map_or(1, |paddings| paddings[j]);
+                            doc += self.arena.text(",") + self.arena.text(" ".repeat(padding));
+                        }
+                    }
+                }
+                SyntaxKind::Comma => doc += self.arena.text(","),
+                SyntaxKind::Semicolon => doc += self.arena.text(";"),
+                _ => {}
+            }
+        }
+        Some((doc.nest(self.config.tab_spaces as isize) + self.arena.hardline()).parens())
+    }
+
+    /// Convert an arg in math, or a cell in a row of args.
+    fn convert_math_arg(&'a self, node: &'a SyntaxNode) -> Option<ArenaDoc<'a>> {
+        if node.kind() == SyntaxKind::Space {
+            return None;
+        }
+        if let Some(math) = node.cast::<Math>() {
+            // Spaces around an arg are not part of it.
+            let children = math.to_untyped().children().as_slice();
+            let start = children
+                .iter()
+                .position(|child| child.kind() != SyntaxKind::Space)
+                .unwrap_or(children.len());
+            let end = children
+                .iter()
+                .rposition(|child| child.kind() != SyntaxKind::Space)
+                .map_or(start, |end| end + 1);
+            return Some(self.convert_math_children(&children[start..end]));
+        }
+        node.cast().map(|arg| self.convert_arg(arg))
+    }
+
     pub(super) fn convert_arg(&'a self, arg: Arg<'a>) -> ArenaDoc<'a> {
         match arg {
             Arg::Pos(p) => self.convert_expr(p),
@@ -139,3 +262,123 @@ impl<'a> PrettyPrinter<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::format, Config, TrailingComma};
+
+    #[test]
+    fn test_args_blank_lines() {
+        let content = "#figure(\n  image(\"a.png\"),\n\n  caption: [A],\n  kind: image,\n)\n";
+        assert_eq!(format(content, Config::new()), content);
+
+        let content = "#f(aaaa, bbbb,\n\n  cccc)\n";
+        assert_eq!(format(content, Config::new()), "#f(aaaa, bbbb, cccc)\n");
+        assert_eq!(
+            format(content, Config::new().with_width(10)),
+            "#f(\n  aaaa,\n  bbbb,\n\n  cccc,\n)\n"
+        );
+    }
+
+    #[test]
+    fn test_trailing_content_block() {
+        let content = "#f(\n  aaaa,\n  bbbb,\n)[body]\n";
+        assert_eq!(format(content, Config::new()), content);
+
+        let content = "#f(aaaa, bbbb, cccc)[body][more]\n";
+        assert_eq!(
+            format(content, Config::new().with_width(10)),
+            "#f(\n  aaaa,\n  bbbb,\n  cccc,\n)[body][more]\n"
+        );
+    }
+
+    #[test]
+    fn test_set_rule_args() {
+        let content = "#set text(size: 12pt, fill: black, font: \"New Computer Modern\")\n";
+        assert_eq!(format(content, Config::new()), content);
+        let expected = "(\n  size: 12pt,\n  fill: black,\n  font: \"New Computer Modern\",\n)\n";
+        assert_eq!(
+            format(content, Config::new().with_width(40)),
+            format!("#set text{expected}")
+        );
+        let call = content.replacen("#set ", "#", 1);
+        assert_eq!(
+            format(&call, Config::new().with_width(40)),
+            format!("#text{expected}")
+        );
+
+        let never = Config::new()
+            .with_width(40)
+            .with_trailing_comma(TrailingComma::Never);
+        assert_eq!(
+            format(content, never),
+            "#set text(\n  size: 12pt,\n  fill: black,\n  font: \"New Computer Modern\"\n)\n"
+        );
+    }
+
+    #[test]
+    fn test_trailing_comment() {
+        let content = "#f(\n  aaaa,\n  bbbb,\n  // trailing\n)\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(format(content, Config::new().with_width(0)), content);
+        assert_eq!(
+            format("#f(aaaa, bbbb // trailing\n)\n", Config::new()),
+            "#f(\n  aaaa,\n  bbbb, // trailing\n)\n"
+        );
+        assert_eq!(
+            format("#f(aaaa, bbbb, /* trailing */)\n", Config::new()),
+            "#f(aaaa, bbbb /* trailing */)\n"
+        );
+    }
+
+    #[test]
+    fn test_leading_comment() {
+        let content = "#foo(\n  // x\n  1,\n  2,\n)\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(format(content, Config::new().with_width(0)), content);
+        assert_eq!(format("#foo(\n  // x\n  1, 2)\n", Config::new()), content);
+
+        let content = "#foo(/* x */ 1, 2)\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_width(15)),
+            "#foo(\n  /* x */ 1,\n  2,\n)\n"
+        );
+    }
+
+    #[test]
+    fn test_comment_after_callee() {
+        // Args must follow the callee directly, so this is not a call but `foo` followed by text.
+        let content = "#foo /* note */ (1, 2)\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(format(content, Config::new().with_width(0)), content);
+    }
+
+    #[test]
+    fn test_math_args() {
+        let content = "$ vec(1, 2, 3) + mat(1, 2; 3, 4) + f(x,y) $\n";
+        assert_eq!(format(content, Config::new()), content);
+
+        let content = "$ vec(x + 1, y + 2, z + 3, w + 4) $\n";
+        let config = Config::new().with_width(20).with_break_math_args(true);
+        assert_eq!(
+            format(content, config.clone()),
+            "$\n  vec(\n    x + 1,\n    y + 2,\n    z + 3,\n    w + 4\n  )\n$\n"
+        );
+        // Args are kept as they are by default.
+        assert_eq!(
+            format(content, Config::new().with_width(20)),
+            "$\n  vec(x + 1, y + 2, z + 3, w + 4)\n$\n"
+        );
+        // Args in inline equations are kept as they are.
+        let content = "$vec(x + 1, y + 2, z + 3, w + 4)$\n";
+        assert_eq!(format(content, config.clone()), content);
+        // Empty args and trailing commas are kept.
+        for args in ["sin(x,,,)", "f(x, y,)"] {
+            assert_eq!(
+                format(&format!("$ {args} $\n"), config.clone().with_width(5)),
+                format!("$\n  {args}\n$\n")
+            );
+        }
+    }
+}
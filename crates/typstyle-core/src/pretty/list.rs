@@ -1,7 +1,7 @@
 use pretty::DocAllocator;
 use typst_syntax::{ast::*, SyntaxKind, SyntaxNode};
 
-use crate::ext::StrExt;
+use crate::{ext::StrExt, TrailingComma};
 
 use super::{doc_ext::DocExt, style::FoldStyle, util::is_comment_node, ArenaDoc, PrettyPrinter};
 
@@ -17,6 +17,9 @@ enum Item<'a> {
     },
     /// Linebreaks
     Linebreak(usize),
+    /// A region between `@typstyle off` and `@typstyle on`, which is kept as is.
+    /// It may contain several items and their separators.
+    Verbatim(ArenaDoc<'a>),
 }
 
 pub struct ListStylist<'a> {
@@ -36,6 +39,8 @@ pub struct ListStylist<'a> {
 pub struct ListStyle {
     /// The separator between items.
     pub separator: &'static str,
+    /// Whether to omit the separators when the list is broken.
+    pub flat_separator_only: bool,
     /// The delimiter of the list.
     pub delim: (&'static str, &'static str),
     /// Whether to add an addition space inside the delimiters if the list is flat.
@@ -48,22 +53,41 @@ pub struct ListStyle {
     pub omit_delim_flat: bool,
     /// Whether can omit the delimiter if the list is empty.
     pub omit_delim_empty: bool,
+    /// When to add a trailing separator.
+    pub trailing_sep: TrailingComma,
+    /// Whether to align attached comments of consecutive items when the list is broken.
+    pub align_comments: bool,
 }
 
 impl Default for ListStyle {
     fn default() -> Self {
         Self {
             separator: ",",
+            flat_separator_only: false,
             delim: ("(", ")"),
             add_delim_space: false,
             add_trailing_sep_single: false,
             omit_delim_single: false,
             omit_delim_flat: false,
             omit_delim_empty: false,
+            trailing_sep: TrailingComma::Multiline,
+            align_comments: false,
         }
     }
 }
 
+impl ListStyle {
+    /// Whether a separator is needed after the last item.
+    fn need_trailing_sep(&self, is_single: bool, is_flat: bool) -> bool {
+        (is_single && self.add_trailing_sep_single)
+            || match self.trailing_sep {
+                TrailingComma::Always => true,
+                TrailingComma::Never => false,
+                TrailingComma::Multiline => !is_flat,
+            }
+    }
+}
+
 impl<'a> ListStylist<'a> {
     pub fn new(printer: &'a PrettyPrinter<'a>) -> Self {
         Self {
@@ -141,8 +165,20 @@ impl<'a> ListStylist<'a> {
         // Can break line after front attachments.
         // If the back attachment appears before the comma, the comma is move to its front if multiline.
 
-        for node in iterable {
-            if let Some(item_body) = item_checker(node) {
+        let attr_store = self.printer.attr_store;
+        let is_in_region =
+            |node: &SyntaxNode| attr_store.is_format_disabled(node) && !is_comment_node(node);
+        let mut iterable = iterable.peekable();
+        while let Some(node) = iterable.next() {
+            // Several disabled nodes in a row can only come from a region, which may hold
+            // separators and spaces as well. A single disabled item is converted as usual.
+            if is_in_region(node) && iterable.peek().is_some_and(|next| is_in_region(next)) {
+                let mut text = node.clone().into_text().to_string();
+                while let Some(next) = iterable.next_if(|next| is_in_region(next)) {
+                    text.push_str(&next.clone().into_text());
+                }
+                self.add_verbatim(text);
+            } else if let Some(item_body) = item_checker(node) {
                 self.add_item(item_body);
             } else {
                 self.process_trivia(node);
@@ -173,6 +209,16 @@ impl<'a> ListStylist<'a> {
         self.can_attach = true;
     }
 
+    /// Add a disabled region, which is put on its own lines.
+    fn add_verbatim(&mut self, text: String) {
+        self.detach_comments();
+        self.item_count += 1;
+        self.fold_style = FoldStyle::Never;
+        self.items
+            .push(Item::Verbatim(self.printer.arena.text(text)));
+        self.can_attach = false;
+    }
+
     /// Handle non-items.
     fn process_trivia(&mut self, node: &'a SyntaxNode) {
         if is_comment_node(node) {
@@ -239,6 +285,40 @@ impl<'a> ListStylist<'a> {
 }
 
 impl<'a> ListStylist<'a> {
+    /// Get the paddings before attached comments, so that the comments are aligned
+    /// within each run of consecutive single-line items with attached comments.
+    fn get_comment_paddings(&self) -> Vec<usize> {
+        fn flush(run: &mut Vec<(usize, usize)>, paddings: &mut [usize]) {
+            let max_width = run
+                .iter()
+                .map(|&(_, width)| width)
+                .max()
+                .unwrap_or_default();
+            for (i, width) in run.drain(..) {
+                paddings[i] = max_width - width;
+            }
+        }
+
+        let mut paddings = vec![0; self.items.len()];
+        let mut run = vec![];
+        for (i, item) in self.items.iter().enumerate() {
+            if let Item::Commented {
+                body,
+                after: Some(_),
+            } = item
+            {
+                let body = body.pretty(self.printer.config.render_width()).to_string();
+                if !body.contains('\n') {
+                    run.push((i, body.chars().count()));
+                    continue;
+                }
+            }
+            flush(&mut run, &mut paddings);
+        }
+        flush(&mut run, &mut paddings);
+        paddings
+    }
+
     /// Create Doc from items in self.
     ///
     /// For attached comments:
@@ -260,6 +340,11 @@ impl<'a> ListStylist<'a> {
 
         let is_single = self.item_count == 1;
         let sep = arena.text(sty.separator);
+        let break_sep = if sty.flat_separator_only {
+            arena.nil()
+        } else {
+            sep.clone()
+        };
         let indent = self.printer.config.tab_spaces;
         let fold_style = if self.has_line_comment {
             FoldStyle::Never
@@ -268,14 +353,34 @@ impl<'a> ListStylist<'a> {
         };
         match fold_style {
             FoldStyle::Never => {
+                let paddings = if sty.align_comments {
+                    self.get_comment_paddings()
+                } else {
+                    vec![0; self.items.len()]
+                };
                 let mut inner = arena.nil();
-                for item in self.items.into_iter() {
+                let mut count = 0;
+                for (item, padding) in self.items.into_iter().zip(paddings) {
                     match item {
                         Item::Comment(cmt) => inner += cmt + arena.hardline(),
                         Item::Commented { body, after } => {
-                            inner += body + sep.clone() + after + arena.hardline();
+                            count += 1;
+                            let is_last = count == self.item_count;
+                            inner += body;
+                            if !is_last || sty.need_trailing_sep(is_single, false) {
+                                inner += break_sep.clone();
+                            }
+                            if padding > 0 {
+                                inner += arena.text(" ".repeat(padding));
+                            }
+                            inner += after;
+                            inner += arena.hardline();
                         }
                         Item::Linebreak(n) => inner += arena.hardline().repeat_n(n),
+                        Item::Verbatim(text) => {
+                            count += 1;
+                            inner += text + arena.hardline();
+                        }
                     }
                 }
                 (arena.hardline() + inner)
@@ -284,19 +389,22 @@ impl<'a> ListStylist<'a> {
             }
             FoldStyle::Always => {
                 let mut inner = arena.nil();
-                for (i, item) in self.items.into_iter().enumerate() {
+                let mut count = 0;
+                for item in self.items.into_iter() {
                     match item {
                         Item::Comment(cmt) => inner += cmt,
                         Item::Commented { body, after } => {
+                            count += 1;
                             inner += body + after;
-                            if i + 1 != self.item_count {
+                            if count != self.item_count {
                                 inner += sep.clone() + arena.space();
-                            } else if is_single && sty.add_trailing_sep_single {
-                                // trailing comma for one-size array
+                            } else if sty.need_trailing_sep(is_single, true) {
                                 inner += sep.clone();
                             }
                         }
                         Item::Linebreak(_) => (),
+                        // Lists with regions are never folded.
+                        Item::Verbatim(_) => unreachable!(),
                     }
                 }
                 if is_single && sty.omit_delim_single || sty.omit_delim_flat {
@@ -309,39 +417,62 @@ impl<'a> ListStylist<'a> {
                     inner.enclose(delim.0, delim.1)
                 }
             }
-            FoldStyle::Fit => {
+            FoldStyle::Fit | FoldStyle::Fill => {
+                // Items with comments are not packed.
+                let fill = fold_style == FoldStyle::Fill && !self.has_comment;
                 let mut inner = arena.nil();
-                for (i, item) in self.items.into_iter().enumerate() {
-                    let is_last = i + 1 == self.item_count;
+                let mut count = 0;
+                for item in self.items.into_iter() {
+                    // Detached comments are not counted as items.
+                    if matches!(item, Item::Commented { .. }) {
+                        count += 1;
+                    }
+                    let is_last = count == self.item_count;
                     match item {
                         Item::Comment(cmt) => inner += cmt + arena.line(),
                         Item::Commented {
                             body,
                             after: Option::None,
                         } => {
-                            let follow = if is_single && sty.add_trailing_sep_single || !is_last {
-                                sep.clone()
+                            let sep_if = |cond| if cond { sep.clone() } else { arena.nil() };
+                            let follow = if !is_last {
+                                break_sep.clone().flat_alt(sep.clone())
                             } else {
-                                sep.clone().flat_alt(arena.nil())
+                                sep_if(sty.need_trailing_sep(is_single, false))
+                                    .flat_alt(sep_if(sty.need_trailing_sep(is_single, true)))
+                            };
+                            let ln = if is_last {
+                                arena.line_()
+                            } else if fill {
+                                arena.line().group()
+                            } else {
+                                arena.line()
                             };
-                            let ln = if is_last { arena.line_() } else { arena.line() };
                             inner += body + follow + ln;
                         }
                         Item::Commented {
                             body,
                             after: Some(after),
                         } => {
-                            let follow_break = sep.clone() + after.clone();
-                            let follow_flat =
-                                if !is_last || is_single && sty.add_trailing_sep_single {
-                                    after + sep.clone()
+                            let follow_break =
+                                if !is_last || sty.need_trailing_sep(is_single, false) {
+                                    break_sep.clone() + after.clone()
                                 } else {
-                                    after
+                                    after.clone()
                                 };
+                            let follow_flat = if !is_last || sty.need_trailing_sep(is_single, true)
+                            {
+                                after + sep.clone()
+                            } else {
+                                after
+                            };
                             let ln = if is_last { arena.line_() } else { arena.line() };
                             inner += body + follow_break.flat_alt(follow_flat) + ln;
                         }
-                        Item::Linebreak(n) => inner += arena.line().repeat_n(n),
+                        // Blank lines are kept only when the list breaks.
+                        Item::Linebreak(n) => inner += arena.line_().repeat_n(n),
+                        // Lists with regions are never folded.
+                        Item::Verbatim(_) => unreachable!(),
                     }
                 }
                 if is_single && sty.omit_delim_single {
@@ -4,14 +4,12 @@ use typst_syntax::{ast::*, SyntaxKind, SyntaxNode};
 use crate::{ext::StrExt, pretty::mode::Mode};
 
 use super::{
-    doc_ext::DocExt,
     flow::FlowItem,
     trivia_strip_prefix,
     util::{is_comment_node, is_only_one_and},
     ArenaDoc, PrettyPrinter,
 };
 
-#[allow(unused)]
 #[derive(Debug, PartialEq, Eq)]
 enum MarkupScope {
     /// The top-level markup.
@@ -25,11 +23,20 @@ enum MarkupScope {
 }
 
 impl<'a> PrettyPrinter<'a> {
+    /// Convert the markup of a whole document, typically the root of a source.
     pub fn convert_markup(&'a self, markup: Markup<'a>) -> ArenaDoc<'a> {
         self.convert_markup_impl(markup, MarkupScope::Document)
     }
 
     pub(super) fn convert_content_block(&'a self, content_block: ContentBlock<'a>) -> ArenaDoc<'a> {
+        let is_blank = content_block
+            .body()
+            .to_untyped()
+            .children()
+            .all(|child| matches!(child.kind(), SyntaxKind::Space | SyntaxKind::Parbreak));
+        if self.config.collapse_empty_content_blocks && is_blank {
+            return self.arena.text("[]");
+        }
         let content = self
             .convert_markup_impl(content_block.body(), MarkupScope::ContentBlock)
             .nest(self.config.tab_spaces as isize);
@@ -37,12 +44,12 @@ impl<'a> PrettyPrinter<'a> {
     }
 
     pub(super) fn convert_strong(&'a self, strong: Strong<'a>) -> ArenaDoc<'a> {
-        let body = self.convert_markup(strong.body());
+        let body = self.convert_markup_impl(strong.body(), MarkupScope::Strong);
         body.enclose("*", "*")
     }
 
     pub(super) fn convert_emph(&'a self, emph: Emph<'a>) -> ArenaDoc<'a> {
-        let body = self.convert_markup(emph.body());
+        let body = self.convert_markup_impl(emph.body(), MarkupScope::Strong);
         body.enclose("_", "_")
     }
 
@@ -59,31 +66,41 @@ impl<'a> PrettyPrinter<'a> {
     }
 
     pub(super) fn convert_list_item(&'a self, list_item: ListItem<'a>) -> ArenaDoc<'a> {
-        self.convert_list_item_like(list_item.to_untyped())
+        self.convert_list_item_like(list_item.to_untyped(), ItemAdjustment::default())
     }
 
     pub(super) fn convert_enum_item(&'a self, enum_item: EnumItem<'a>) -> ArenaDoc<'a> {
-        self.convert_list_item_like(enum_item.to_untyped())
+        self.convert_list_item_like(enum_item.to_untyped(), ItemAdjustment::default())
     }
 
     pub(super) fn convert_term_item(&'a self, term_item: TermItem<'a>) -> ArenaDoc<'a> {
-        self.convert_list_item_like(term_item.to_untyped())
+        self.convert_list_item_like(term_item.to_untyped(), ItemAdjustment::default())
     }
 
-    fn convert_list_item_like(&'a self, item: &'a SyntaxNode) -> ArenaDoc<'a> {
+    fn convert_list_item_like(
+        &'a self,
+        item: &'a SyntaxNode,
+        adjustment: ItemAdjustment,
+    ) -> ArenaDoc<'a> {
         self.convert_flow_like(item, |child| match child.kind() {
             SyntaxKind::ListMarker | SyntaxKind::EnumMarker | SyntaxKind::TermMarker => {
-                FlowItem::spaced(self.arena.text(child.text().as_str()))
+                FlowItem::spaced(match adjustment.number {
+                    Some(number) if child.kind() == SyntaxKind::EnumMarker => {
+                        self.arena.text(format!("{number}."))
+                    }
+                    _ => self.arena.text(child.text().as_str()),
+                })
             }
-            SyntaxKind::Colon => FlowItem::tight_spaced(self.arena.text(child.text().as_str())),
+            SyntaxKind::Colon => FlowItem::tight_spaced(
+                self.arena.text(child.text().as_str())
+                    + self.arena.text(" ".repeat(adjustment.term_padding)),
+            ),
             SyntaxKind::Space if child.text().has_linebreak() => {
                 FlowItem::tight(self.arena.hardline())
             }
-            SyntaxKind::Parbreak => FlowItem::tight(
-                self.arena
-                    .hardline()
-                    .repeat_n(child.text().count_linebreaks()),
-            ),
+            SyntaxKind::Parbreak => {
+                FlowItem::tight(self.convert_parbreak(child.cast().expect("parbreak")))
+            }
             SyntaxKind::Markup if child.children().next().is_some() => {
                 // empty markup is ignored here
                 FlowItem::spaced(
@@ -95,6 +112,109 @@ impl<'a> PrettyPrinter<'a> {
         .nest(self.config.tab_spaces as isize)
     }
 
+    /// Convert an item in a markup line with text, which keeps the original format.
+    /// When `collapse_spaces` is enabled, runs of spaces in markup are collapsed.
+    fn convert_text_line_item(&'a self, node: &'a SyntaxNode) -> ArenaDoc<'a> {
+        if self.attr_store.is_format_disabled(node) {
+            return self.format_disabled(node);
+        }
+        if node.kind() == SyntaxKind::LineComment {
+            return self.convert_comment(node);
+        }
+        if self.config.reflow_prose && node.kind() == SyntaxKind::Text {
+            return self.reflow_text(node.text());
+        }
+        if !self.config.collapse_spaces {
+            return self.format_disabled(node);
+        }
+        let mut text = String::new();
+        collect_collapsed_text(node, &mut text);
+        self.arena.text(text)
+    }
+
+    /// Split text into words that can be wrapped.
+    fn reflow_text(&'a self, text: &'a str) -> ArenaDoc<'a> {
+        let mut doc = self.arena.nil();
+        for (i, word) in text.split(' ').enumerate() {
+            if i > 0 {
+                doc += if is_marker_like(word) {
+                    self.arena.space()
+                } else {
+                    self.arena.softline()
+                };
+            }
+            doc += self.arena.text(word);
+        }
+        doc
+    }
+
+    /// Convert a space in a paragraph, which can be wrapped when the line is too long.
+    /// Linebreaks are joined unless they are forced by `\`, comments, or non-text lines.
+    fn reflow_space(&'a self, items: &[MarkupItem<'a>], index: usize) -> ArenaDoc<'a> {
+        let space = items[index].node;
+        let prev = index.checked_sub(1).map(|i| items[i].node);
+        let next = items.get(index + 1);
+        if prev.is_some_and(|prev| prev.kind() == SyntaxKind::Linebreak) {
+            return self.arena.hardline();
+        }
+        if space.text().has_linebreak()
+            && (prev.is_some_and(|prev| prev.kind() == SyntaxKind::LineComment)
+                || !next.is_some_and(|next| next.format_disabled && !is_comment_node(next.node)))
+        {
+            return self.arena.hardline();
+        }
+        match next {
+            Some(next)
+                if next.node.kind() == SyntaxKind::Text
+                    && is_marker_like(next.node.text().split(' ').next().unwrap_or_default()) =>
+            {
+                self.arena.space()
+            }
+            _ => self.arena.softline(),
+        }
+    }
+
+    /// Get the paddings after the colons of term items, so that the descriptions of consecutive
+    /// term items start at the same column. A group of term items is broken by blank lines or
+    /// other siblings, and is left unaligned if any item has comments or spans multiple lines.
+    fn get_term_paddings(&'a self, items: &[MarkupItem<'a>]) -> Vec<usize> {
+        let mut paddings = vec![0; items.len()];
+        // Indices and term widths of the items in the current group.
+        let mut group: Vec<(usize, Option<usize>)> = vec![];
+        let mut flush = |group: &mut Vec<(usize, Option<usize>)>| {
+            let widths: Option<Vec<usize>> = group.iter().map(|&(_, width)| width).collect();
+            if let Some(widths) = widths.filter(|widths| widths.len() > 1) {
+                let max_width = widths.iter().copied().max().unwrap_or_default();
+                for (&(i, _), width) in group.iter().zip(widths) {
+                    paddings[i] = max_width - width;
+                }
+            }
+            group.clear();
+        };
+        for (i, item) in items.iter().enumerate() {
+            match item.node.cast::<TermItem>() {
+                Some(term_item) if !item.format_disabled => {
+                    group.push((i, self.get_term_width(term_item)));
+                }
+                _ if item.node.kind() == SyntaxKind::Space => {}
+                _ => flush(&mut group),
+            }
+        }
+        flush(&mut group);
+        paddings
+    }
+
+    /// Get the width of the formatted term, or `None` if the item cannot be aligned.
+    fn get_term_width(&'a self, term_item: TermItem<'a>) -> Option<usize> {
+        let node = term_item.to_untyped();
+        if self.attr_store.is_unformattable(node) || node.clone().into_text().has_linebreak() {
+            return None;
+        }
+        let term = self.convert_markup_impl(term_item.term(), MarkupScope::Item);
+        let text = term.pretty(self.config.render_width()).to_string();
+        (!text.contains('\n')).then(|| text.chars().count())
+    }
+
     fn convert_markup_impl(&'a self, markup: Markup<'a>, scope: MarkupScope) -> ArenaDoc<'a> {
         let _g = self.with_mode(Mode::Markup);
 
@@ -107,23 +227,56 @@ impl<'a> PrettyPrinter<'a> {
         let items = collect_markup_items(markup);
 
         let mut doc = self.arena.nil();
-        for MarkupItem {
-            node,
-            format_disabled,
-        } in items.items
-        {
+        let mut next_enum_number = None;
+        let term_paddings = if self.config.align_terms {
+            self.get_term_paddings(&items.items)
+        } else {
+            vec![]
+        };
+        for (i, item) in items.items.iter().enumerate() {
+            let &MarkupItem {
+                node,
+                format_disabled,
+            } = item;
             if let Some(space) = node.cast::<Space>() {
-                doc += self.convert_space(space);
+                doc += if self.attr_store.is_format_disabled(node) {
+                    self.format_disabled(node)
+                } else if self.config.reflow_prose && format_disabled {
+                    self.reflow_space(&items.items, i)
+                } else {
+                    self.convert_space(space)
+                };
                 continue;
             }
             if let Some(pb) = node.cast::<Parbreak>() {
-                doc += self.convert_parbreak(pb);
+                doc += if self.attr_store.is_format_disabled(node) {
+                    self.format_disabled(node)
+                } else {
+                    self.convert_parbreak(pb)
+                };
                 continue;
             }
+            let enum_number = match node.cast::<EnumItem>() {
+                Some(enum_item) if self.config.renumber_enums => {
+                    renumber_enum_item(enum_item, &mut next_enum_number)
+                }
+                _ => {
+                    if !is_comment_node(node) {
+                        next_enum_number = None;
+                    }
+                    None
+                }
+            };
+            let adjustment = ItemAdjustment {
+                number: enum_number,
+                term_padding: term_paddings.get(i).copied().unwrap_or_default(),
+            };
             doc += if format_disabled {
-                self.format_disabled(node)
+                self.convert_text_line_item(node)
+            } else if adjustment.is_some() && !self.attr_store.is_format_disabled(node) {
+                self.convert_list_item_like(node, adjustment)
             } else if let Some(expr) = node.cast::<Expr>() {
-                self.convert_expr(expr)
+                self.convert_embedded_expr(expr)
             } else if is_comment_node(node) {
                 self.convert_comment(node)
             } else {
@@ -136,6 +289,14 @@ impl<'a> PrettyPrinter<'a> {
         let has_line_break = self.attr_store.is_multiline(markup.to_untyped());
         let is_symmetric = items.start_bound != Boundary::Nil && items.end_bound != Boundary::Nil;
         let get_delim = |bound: Boundary| {
+            if scope == MarkupScope::Strong {
+                // spaces inside the markers are significant, so keep them as they are
+                return match bound {
+                    Boundary::Nil | Boundary::NilOrBreak => self.arena.nil(),
+                    Boundary::SpaceOrBreak | Boundary::WeakSpaceOrBreak => self.arena.space(),
+                    Boundary::Break | Boundary::WeakBreak => self.arena.hardline(),
+                };
+            }
             if scope == MarkupScope::Document || scope == MarkupScope::Item {
                 // should not add extra lines to the document
                 return if bound == Boundary::Break {
@@ -170,6 +331,61 @@ impl<'a> PrettyPrinter<'a> {
     }
 }
 
+/// Collect the text of a node, replacing spaces in markup with a single space.
+/// Spaces in code, raw, and equations are kept.
+fn collect_collapsed_text(node: &SyntaxNode, text: &mut String) {
+    match node.kind() {
+        SyntaxKind::Space if !node.text().has_linebreak() => text.push(' '),
+        SyntaxKind::Markup | SyntaxKind::Strong | SyntaxKind::Emph => {
+            for child in node.children() {
+                collect_collapsed_text(child, text);
+            }
+        }
+        _ => text.push_str(&node.clone().into_text()),
+    }
+}
+
+/// Changes to a list, enum, or term item that depend on its siblings.
+#[derive(Debug, Default, Clone, Copy)]
+struct ItemAdjustment {
+    /// The number replacing the enum marker.
+    number: Option<u64>,
+    /// Extra spaces after the colon of a term item.
+    term_padding: usize,
+}
+
+impl ItemAdjustment {
+    fn is_some(&self) -> bool {
+        self.number.is_some() || self.term_padding > 0
+    }
+}
+
+/// Get the number of an enum item in a run of consecutive items, ascending from the first one.
+/// Returns `None` if the item has no explicit number, which is kept as is.
+fn renumber_enum_item(enum_item: EnumItem, next_number: &mut Option<u64>) -> Option<u64> {
+    let number = enum_item
+        .to_untyped()
+        .children()
+        .find(|child| child.kind() == SyntaxKind::EnumMarker)
+        .and_then(|marker| marker.text().strip_suffix('.')?.parse::<u64>().ok());
+    let current = match (number, *next_number) {
+        (Some(_), Some(next)) => next,
+        (Some(number), None) => number,
+        (None, next) => next.unwrap_or(1),
+    };
+    *next_number = Some(current + 1);
+    number.map(|_| current)
+}
+
+/// Whether the word would be parsed as a marker of a block element at the start of a line.
+fn is_marker_like(word: &str) -> bool {
+    matches!(word, "-" | "+" | "/")
+        || (!word.is_empty() && word.chars().all(|c| c == '='))
+        || word
+            .strip_suffix('.')
+            .is_some_and(|num| !num.is_empty() && num.chars().all(|c| c.is_ascii_digit()))
+}
+
 struct MarkupItem<'a> {
     node: &'a SyntaxNode,
     format_disabled: bool,
@@ -334,3 +550,169 @@ fn is_block_elem_untyped(it: &'_ SyntaxNode) -> bool {
         SyntaxKind::ListItem | SyntaxKind::EnumItem | SyntaxKind::TermItem
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::format, Config};
+
+    #[test]
+    fn test_list_markers() {
+        // `-` is the only bullet marker in typst, while `+` starts an auto-numbered enum item,
+        // so markers are never rewritten. Nested items are re-indented by `tab_spaces`.
+        let content = "- a\n+ b\n    - c\n    + d\n/ e: f\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "- a\n+ b\n  - c\n  + d\n/ e: f\n"
+        );
+    }
+
+    #[test]
+    fn test_renumber_enums() {
+        let content = "1. a\n3. b\n  5. c\n  5. d\n\n7. e\n+ f\n1. g\n\nText\n\n+ h\n+ i\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_renumber_enums(true)),
+            "1. a\n2. b\n  5. c\n  6. d\n\n3. e\n+ f\n5. g\n\nText\n\n+ h\n+ i\n"
+        );
+    }
+
+    #[test]
+    fn test_align_terms() {
+        let content = "/ a: x\n/ long: y\n/ *bold*: z\n\n/ b: x\n/ cc: y\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_align_terms(true)),
+            "/ a:      x\n/ long:   y\n/ *bold*: z\n\n/ b:  x\n/ cc: y\n"
+        );
+    }
+
+    #[test]
+    fn test_parbreak_blank_lines() {
+        let content = "a\n\n\n\n\nb\n\n- c\n\n\n\n\n  d\n";
+        assert_eq!(
+            format(content, Config::new().with_blank_lines_upper_bound(0)),
+            "a\n\nb\n\n- c\n\n  d\n"
+        );
+    }
+
+    #[test]
+    fn test_collapse_empty_content_blocks() {
+        let config = Config::new().with_collapse_empty_content_blocks(true);
+        for content in ["#f[]\n", "#f[ ]\n", "#f[\n]\n", "#f[\n\n]\n"] {
+            assert_eq!(format(content, config.clone()), "#f[]\n");
+        }
+        for content in ["#f[~]\n", "#f[\u{a0}]\n", "#f[ a ]\n"] {
+            assert_eq!(format(content, config.clone()), content);
+        }
+    }
+
+    #[test]
+    fn test_collapse_spaces() {
+        let content = "Hello *foo  bar* and _a  b_ `x  y` #f(\"c  d\")\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_collapse_spaces(true)),
+            "Hello *foo bar* and _a b_ `x  y` #f(\"c  d\")\n"
+        );
+        let content = "-  item  *a  b*\n";
+        assert_eq!(
+            format(content, Config::new().with_collapse_spaces(true)),
+            "- item *a b*\n"
+        );
+    }
+
+    #[test]
+    fn test_reflow_prose() {
+        let config = Config::new().with_width(20).with_reflow_prose(true);
+        let content =
+            "Lorem ipsum dolor sit amet, *consectetur adipiscing* elit.\nSed do eiusmod.\n";
+        assert_eq!(
+            format(content, config.clone()),
+            "Lorem ipsum dolor\nsit amet,\n*consectetur adipiscing*\nelit. Sed do\neiusmod.\n"
+        );
+        assert_eq!(format(content, Config::new()), content);
+
+        let config = Config::new().with_reflow_prose(true);
+        assert_eq!(format("foo\nbar\n", config.clone()), "foo bar\n");
+        assert_eq!(format("foo \\\nbar\n", config.clone()), "foo \\\nbar\n");
+        assert_eq!(format("foo\n- bar\n", config.clone()), "foo\n- bar\n");
+        assert_eq!(format("foo\n\nbar\n", config), "foo\n\nbar\n");
+    }
+
+    #[test]
+    fn test_reflow_shorthands() {
+        let config = Config::new().with_width(12).with_reflow_prose(true);
+        assert_eq!(
+            format("aaaa bbbb --- cccc\n", config.clone()),
+            "aaaa bbbb\n--- cccc\n"
+        );
+        assert_eq!(
+            format("aaaa bbbb---cccc\n", config.clone()),
+            "aaaa\nbbbb---cccc\n"
+        );
+        assert_eq!(format("aaaa bbbbb -- c\n", config), "aaaa bbbbb\n-- c\n");
+        let content = "aaaa bbbb --- cccc\n";
+        assert_eq!(format(content, Config::new().with_width(12)), content);
+    }
+
+    #[test]
+    fn test_labels() {
+        let content = "= Heading <h:intro>\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format("=   Heading   <h:intro>  \n", Config::new()),
+            content
+        );
+
+        // A label is kept tight, spaced, or on its own line after the content it follows.
+        for content in [
+            "#figure([A])<fig:a>\n",
+            "#figure([A]) <fig:a>\n",
+            "#figure([A])\n<fig:a>\n",
+        ] {
+            assert_eq!(format(content, Config::new()), content);
+        }
+        assert_eq!(
+            format("#figure([A])   <fig:a>\n", Config::new()),
+            "#figure([A]) <fig:a>\n"
+        );
+    }
+
+    #[test]
+    fn test_escapes() {
+        let content = "\\#foo \\*bar\\* \\$x\\$ a\\\\b\nc\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_collapse_spaces(true)),
+            content
+        );
+        assert_eq!(
+            format(content, Config::new().with_reflow_prose(true)),
+            "\\#foo \\*bar\\* \\$x\\$ a\\\\b c\n"
+        );
+        assert_eq!(
+            format(
+                "aaaa\n\\#bbbb \\- c\n",
+                Config::new().with_width(6).with_reflow_prose(true)
+            ),
+            "aaaa\n\\#bbbb\n\\- c\n"
+        );
+    }
+
+    #[test]
+    fn test_comments_between_statements() {
+        let content = "#let a = 1\n\n// Section\n\n#let b = 2\n/* Note */\n#let c = 3\n";
+        assert_eq!(format(content, Config::new()), content);
+
+        // Blank lines around comments are limited like other blank lines.
+        let content = "#let a = 1\n\n\n\n\n// Section\n\n\n\n#let b = 2\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "#let a = 1\n\n\n// Section\n\n\n#let b = 2\n"
+        );
+        assert_eq!(
+            format(content, Config::new().with_blank_lines_upper_bound(1)),
+            "#let a = 1\n\n// Section\n\n#let b = 2\n"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use pretty::DocAllocator;
+use typst_syntax::{ast::*, SyntaxKind, SyntaxNode};
+
+use super::{util::has_comment_children, ArenaDoc, PrettyPrinter};
+
+/// A cell between alignment points.
+struct Cell<'a> {
+    doc: ArenaDoc<'a>,
+    width: usize,
+    /// Whether there is a space between the alignment point and the cell.
+    space_before: bool,
+}
+
+struct Row<'a> {
+    cells: Vec<Cell<'a>>,
+    linebreak: Option<&'a SyntaxNode>,
+}
+
+impl<'a> PrettyPrinter<'a> {
+    /// Convert the body of a multiline block equation, padding cells so that the
+    /// alignment points (`&`) of all lines are in the same columns.
+    ///
+    /// Returns `None` if there are no alignment points, or the body cannot be aligned safely,
+    /// e.g., it contains comments, disabled nodes, or rows spanning multiple lines.
+    pub(super) fn try_convert_aligned_math(&'a self, math: Math<'a>) -> Option<ArenaDoc<'a>> {
+        let node = math.to_untyped();
+        if has_comment_children(node) || self.attr_store.is_unformattable(node) {
+            return None;
+        }
+        let children = node.children().as_slice();
+        if !children
+            .iter()
+            .any(|child| child.kind() == SyntaxKind::MathAlignPoint)
+        {
+            return None;
+        }
+
+        let mut rows = vec![];
+        let mut rest = children;
+        while !rest.is_empty() {
+            let (row, linebreak) = match rest
+                .iter()
+                .position(|child| child.kind() == SyntaxKind::Linebreak)
+            {
+                Some(i) => {
+                    let row = &rest[..i];
+                    let linebreak = &rest[i];
+                    rest = &rest[i + 1..];
+                    (row, Some(linebreak))
+                }
+                None => {
+                    let row = rest;
+                    rest = &[];
+                    (row, None)
+                }
+            };
+            rows.push(self.convert_math_row(row, linebreak)?);
+        }
+        if rows.len() < 2 {
+            return None;
+        }
+
+        // The last cell of each row is not followed by an alignment point,
+        // so it does not affect the column widths.
+        let mut widths: Vec<usize> = vec![];
+        for row in &rows {
+            for (i, cell) in row.cells.iter().take(row.cells.len() - 1).enumerate() {
+                if i < widths.len() {
+                    widths[i] = widths[i].max(cell.width);
+                } else {
+                    widths.push(cell.width);
+                }
+            }
+        }
+
+        let mut doc = self.arena.nil();
+        for (i, row) in rows.into_iter().enumerate() {
+            if i > 0 {
+                doc += self.arena.hardline();
+            }
+            let cell_count = row.cells.len();
+            for (j, cell) in row.cells.into_iter().enumerate() {
+                if j > 0 {
+                    doc += self.arena.text("&");
+                    if cell.space_before && cell.width > 0 {
+                        doc += self.arena.space();
+                    }
+                }
+                doc += cell.doc;
+                if j + 1 < cell_count {
+                    let padding = widths[j] - cell.width + usize::from(widths[j] > 0);
+                    doc += self.arena.text(" ".repeat(padding));
+                }
+            }
+            if let Some(linebreak) = row.linebreak {
+                doc += self.arena.space() + self.convert_verbatim_untyped(linebreak);
+            }
+        }
+        Some(doc)
+    }
+
+    /// Split a row into cells by alignment points.
+    fn convert_math_row(
+        &'a self,
+        row: &'a [SyntaxNode],
+        linebreak: Option<&'a SyntaxNode>,
+    ) -> Option<Row<'a>> {
+        if row.iter().all(|child| child.kind() == SyntaxKind::Space) {
+            return None;
+        }
+        let mut cells = vec![];
+        for (i, cell) in row
+            .split(|child| child.kind() == SyntaxKind::MathAlignPoint)
+            .enumerate()
+        {
+            if cell
+                .iter()
+                .any(|child| self.attr_store.is_unformattable(child))
+            {
+                return None;
+            }
+            let space_before = i > 0
+                && cell
+                    .first()
+                    .is_some_and(|child| child.kind() == SyntaxKind::Space);
+            let cell = trim_spaces(cell);
+            let doc = self.convert_math_children(cell);
+            let text = doc.pretty(self.config.render_width()).to_string();
+            if text.contains('\n') {
+                return None;
+            }
+            cells.push(Cell {
+                doc,
+                width: text.chars().count(),
+                space_before,
+            });
+        }
+        Some(Row { cells, linebreak })
+    }
+}
+
+fn trim_spaces(nodes: &[SyntaxNode]) -> &[SyntaxNode] {
+    let is_not_space = |node: &SyntaxNode| node.kind() != SyntaxKind::Space;
+    let start = nodes.iter().position(is_not_space).unwrap_or(nodes.len());
+    let end = nodes
+        .iter()
+        .rposition(is_not_space)
+        .map_or(start, |i| i + 1);
+    &nodes[start..end]
+}
@@ -11,6 +11,7 @@ mod func_call;
 mod import;
 mod list;
 mod markup;
+mod math_align;
 mod mode;
 mod parened_expr;
 mod plain;
@@ -22,43 +23,102 @@ pub use mode::Mode;
 use std::cell::RefCell;
 
 use itertools::Itertools;
-use pretty::{Arena, DocAllocator, DocBuilder};
+use pretty::{Arena, DocAllocator, DocBuilder, Render, RenderAnnotated};
 use typst_syntax::{ast::*, SyntaxKind, SyntaxNode};
 
-use crate::{ext::StrExt, AttrStore, Config};
+use crate::{
+    ext::StrExt, AttrStore, Config, MathDelimSpacing, MathSymbolStyle, QuoteStyle, RawFormatters,
+    TrailingComma,
+};
 use doc_ext::DocExt;
 use style::FoldStyle;
 
-pub type ArenaDoc<'a> = DocBuilder<'a, Arena<'a>>;
+pub type ArenaDoc<'a> = DocBuilder<'a, Arena<'a, Annotation>, Annotation>;
 
+type ExprConverter<'a> = fn(&'a PrettyPrinter<'a>, Expr<'a>) -> ArenaDoc<'a>;
+
+/// Annotations in pretty documents, which are seen by renderers implementing
+/// [`RenderAnnotated`](pretty::RenderAnnotated).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Annotation {
+    /// Text whose trailing whitespace is meaningful, such as lines in raw blocks.
+    KeepTrailingWhitespace,
+}
+
+/// Converts typst syntax trees into pretty documents.
+///
+/// [`Typstyle`](crate::Typstyle) is the usual entry point. Use the printer directly to render the
+/// document yourself, e.g., with widths other than `max_width`. The source must have no syntax
+/// errors, and the document borrows the printer, so the printer must outlive it.
+///
+/// ```
+/// use typst_syntax::Source;
+/// use typstyle_core::{pretty::PrettyPrinter, AttrStore, Config};
+///
+/// let source = Source::detached("#let x = (1, 2, 3)\n");
+/// let attr_store = AttrStore::new(source.root());
+/// let printer = PrettyPrinter::new(Config::new(), &attr_store);
+/// let doc = printer.convert_markup(source.root().cast().unwrap());
+/// assert_eq!(doc.pretty(80).to_string(), "#let x = (1, 2, 3)\n");
+/// assert_eq!(doc.pretty(10).to_string(), "#let x = (\n  1,\n  2,\n  3,\n)\n");
+/// ```
+///
+/// Unlike [`Typstyle`](crate::Typstyle), the rendered text is not post-processed. Trailing
+/// whitespace, the final newline, and line endings are left as is.
 pub struct PrettyPrinter<'a> {
     config: Config,
-    attr_store: AttrStore,
+    attr_store: &'a AttrStore,
+    raw_formatters: RawFormatters,
     mode: RefCell<Vec<Mode>>,
-    arena: Arena<'a>,
+    arena: Arena<'a, Annotation>,
 }
 
 impl<'a> PrettyPrinter<'a> {
-    pub fn new(config: Config, attr_store: AttrStore) -> Self {
+    pub fn new(config: Config, attr_store: &'a AttrStore) -> Self {
         Self {
             config,
             attr_store,
+            raw_formatters: RawFormatters::default(),
             mode: vec![].into(),
             arena: Arena::new(),
         }
     }
 
+    pub(crate) fn with_raw_formatters(mut self, raw_formatters: RawFormatters) -> Self {
+        self.raw_formatters = raw_formatters;
+        self
+    }
+
     fn get_fold_style(&self, node: impl AstNode<'a>) -> FoldStyle {
         self.get_fold_style_untyped(node.to_untyped())
     }
 
+    /// Like `get_fold_style`, but packs the items if `fill_lists` is enabled.
+    /// Lists that are broken in the source are packed too, as filled lists are broken themselves
+    /// and must be filled the same way when formatted again.
+    fn get_fill_fold_style(&self, node: impl AstNode<'a>) -> FoldStyle {
+        if self.config.fill_lists {
+            FoldStyle::Fill
+        } else {
+            self.get_fold_style(node)
+        }
+    }
+
     fn get_fold_style_untyped(&self, node: &'a SyntaxNode) -> FoldStyle {
-        if self.attr_store.is_multiline_flavor(node) {
+        if !self.config.compact && self.attr_store.is_multiline_flavor(node) {
             FoldStyle::Never
         } else {
             FoldStyle::Fit
         }
     }
+
+    /// Whether the list has a trailing comma that keeps it broken. See `magic_trailing_comma`.
+    fn has_magic_trailing_comma(&self, node: &'a SyntaxNode) -> bool {
+        self.config.magic_trailing_comma
+            && !self.config.compact
+            && self.config.trailing_comma != TrailingComma::Always
+            && util::has_trailing_comma(node)
+    }
 }
 
 impl<'a> PrettyPrinter<'a> {
@@ -80,6 +140,10 @@ impl<'a> PrettyPrinter<'a> {
     }
 
     fn format_disabled(&'a self, node: &'a SyntaxNode) -> ArenaDoc<'a> {
+        if node.children().len() == 0 {
+            // The text of a leaf can be borrowed, which saves copying most nodes in text lines.
+            return self.convert_verbatim_untyped(node);
+        }
         self.arena.text(node.clone().into_text().to_string())
     }
 
@@ -94,9 +158,83 @@ impl<'a> PrettyPrinter<'a> {
     }
 
     pub fn convert_expr(&'a self, expr: Expr<'a>) -> ArenaDoc<'a> {
+        self.convert_expr_with(expr, Self::convert_expr_impl)
+    }
+
+    /// Convert an expression with `convert`, unless formatting is disabled for it.
+    fn convert_expr_with(&'a self, expr: Expr<'a>, convert: ExprConverter<'a>) -> ArenaDoc<'a> {
         if let Some(res) = self.check_disabled(expr.to_untyped()) {
             return res;
         }
+        let width = self
+            .attr_store
+            .width_override(expr.to_untyped())
+            .or_else(|| self.get_mode_width(expr));
+        match width {
+            Some(width) if !self.config.compact => {
+                self.convert_expr_with_width(expr, width, convert)
+            }
+            _ => convert(self, expr),
+        }
+    }
+
+    /// Get the width of an expression in markup that enters code or math, if set for that mode.
+    fn get_mode_width(&self, expr: Expr<'_>) -> Option<usize> {
+        if !self.current_mode().is_markup() {
+            return None;
+        }
+        match expr {
+            Expr::Equation(_) => self.config.math_width,
+            _ if expr.hash() => self.config.code_width,
+            _ => None,
+        }
+    }
+
+    /// Convert an expression with the width set by a `@typstyle width <n>` comment, or for its
+    /// mode.
+    ///
+    /// As the width of a document cannot change within `doc.pretty`, the expression is rendered
+    /// on its own when the column and indentation where it is placed are known, so that the width
+    /// is counted from the start of the line and lines in raw blocks keep their indentation.
+    /// The rendered lines are then put as they are, and cannot be broken again to fit the width
+    /// of the surrounding document. Overrides nested in the expression are rendered in the same
+    /// way, so the innermost one applies.
+    fn convert_expr_with_width(
+        &'a self,
+        expr: Expr<'a>,
+        width: usize,
+        convert: ExprConverter<'a>,
+    ) -> ArenaDoc<'a> {
+        let doc = convert(self, expr).into_doc();
+        self.arena.column(move |column| {
+            self.arena
+                .nesting(move |nesting| {
+                    let mut rendered = AnnotatedText::default();
+                    let _ = (self.arena.text(" ".repeat(column))
+                        + (self.arena.nil() + doc).nest(nesting as isize))
+                    .render_raw(width, &mut rendered);
+                    // Lines after the first are already indented.
+                    let mut res = self.arena.nil();
+                    for (i, (text, annotation)) in rendered.pieces.iter().enumerate() {
+                        let text = if i == 0 { &text[column..] } else { text };
+                        for (j, line) in text.split('\n').enumerate() {
+                            if j > 0 {
+                                res += self.arena.hardline();
+                            }
+                            let line = self.arena.text(line.to_string());
+                            res += match annotation {
+                                Some(annotation) => line.annotate(*annotation),
+                                None => line,
+                            };
+                        }
+                    }
+                    res.nest(-(nesting as isize)).into_doc()
+                })
+                .into_doc()
+        })
+    }
+
+    fn convert_expr_impl(&'a self, expr: Expr<'a>) -> ArenaDoc<'a> {
         if self.current_mode().is_math() {
             if let Some(res) = self.check_unformattable(expr.to_untyped()) {
                 return res;
@@ -129,18 +267,18 @@ impl<'a> PrettyPrinter<'a> {
             Expr::MathPrimes(mp) => self.convert_math_primes(mp),
             Expr::MathFrac(mf) => self.convert_math_frac(mf),
             Expr::MathRoot(mr) => self.convert_math_root(mr),
-            Expr::MathShorthand(ms) => self.convert_verbatim(ms),
+            Expr::MathShorthand(ms) => self.convert_math_shorthand(ms),
             Expr::Ident(i) => self.convert_ident(i),
             Expr::None(n) => self.convert_verbatim(n),
             Expr::Auto(a) => self.convert_verbatim(a),
             Expr::Bool(b) => self.convert_verbatim(b),
-            Expr::Int(i) => self.convert_verbatim(i),
-            Expr::Float(f) => self.convert_verbatim(f),
-            Expr::Numeric(n) => self.convert_verbatim(n),
-            Expr::Str(s) => self.convert_verbatim(s),
+            Expr::Int(i) => self.convert_numeric(i.to_untyped()),
+            Expr::Float(f) => self.convert_numeric(f.to_untyped()),
+            Expr::Numeric(n) => self.convert_numeric(n.to_untyped()),
+            Expr::Str(s) => self.convert_str(s),
             Expr::Code(c) => self.convert_code_block(c),
             Expr::Content(c) => self.convert_content_block(c),
-            Expr::Parenthesized(p) => self.convert_parenthesized(p),
+            Expr::Parenthesized(p) => self.convert_parenthesized_expr(p),
             Expr::Array(a) => self.convert_array(a),
             Expr::Dict(d) => self.convert_dict(d),
             Expr::Unary(u) => self.convert_unary(u),
@@ -177,6 +315,9 @@ impl<'a> PrettyPrinter<'a> {
         self.convert_trivia(text)
     }
 
+    /// Convert a space. A space with linebreaks becomes a hardline, and any other run of spaces
+    /// collapses to a single space, e.g., `1  +  2` to `1 + 2` in code and math. Markup lines
+    /// with text are kept by `convert_text_line_item` instead.
     fn convert_space(&'a self, space: Space<'a>) -> ArenaDoc<'a> {
         let node = space.to_untyped();
         if node.text().has_linebreak() {
@@ -186,29 +327,137 @@ impl<'a> PrettyPrinter<'a> {
         }
     }
 
+    /// Convert an integer, a float, or a number with a unit, like `2cm` or `50%`.
+    fn convert_numeric(&'a self, node: &'a SyntaxNode) -> ArenaDoc<'a> {
+        match util::normalize_number(node.text()).filter(|_| self.config.normalize_numbers) {
+            Some(text) => self.arena.text(text),
+            None => self.convert_verbatim_untyped(node),
+        }
+    }
+
+    fn convert_str(&'a self, string: Str<'a>) -> ArenaDoc<'a> {
+        let text = string.to_untyped().text();
+        if self.config.quote_style == QuoteStyle::Normalize && !text.has_linebreak() {
+            return self.arena.text(util::normalize_str_escapes(text));
+        }
+        self.convert_verbatim(string)
+    }
+
     fn convert_parbreak(&'a self, parbreak: Parbreak<'a>) -> ArenaDoc<'a> {
-        let newline_count = parbreak.to_untyped().text().count_linebreaks();
+        // Keep at least one blank line, or it is no longer a parbreak.
+        let newline_count = parbreak
+            .to_untyped()
+            .text()
+            .count_linebreaks()
+            .min(self.config.blank_lines_upper_bound + 1)
+            .max(2);
         self.arena.hardline().repeat_n(newline_count)
     }
 
     fn convert_raw(&'a self, raw: Raw<'a>) -> ArenaDoc<'a> {
+        if let Some(res) = self.try_convert_raw_with_formatter(raw) {
+            return res;
+        }
+
+        let fence = raw
+            .block()
+            .then(|| get_raw_fence(raw.lines().map(|line| line.get().as_str())));
+        let raw_indent = self.attr_store.raw_indent(raw.to_untyped());
         let mut doc = self.arena.nil();
+        // The indentation of the line started by the last line break, if any.
+        let mut indent = None;
         for child in raw.to_untyped().children() {
-            if let Some(delim) = child.cast::<RawDelim>() {
-                doc += self.convert_verbatim(delim);
+            let child_doc = if let Some(delim) = child.cast::<RawDelim>() {
+                match &fence {
+                    Some(fence) => self.arena.text(fence.clone()),
+                    None => self.convert_verbatim(delim),
+                }
             } else if let Some(lang) = child.cast::<RawLang>() {
-                doc += self.convert_verbatim(lang);
+                self.convert_verbatim(lang)
             } else if let Some(line) = child.cast::<Text>() {
-                doc += self.convert_trivia(line);
+                keep_trailing_whitespace(self.convert_trivia(line), line.get())
             } else if child.kind() == SyntaxKind::RawTrimmed {
-                if child.text().has_linebreak() {
-                    doc += self.arena.hardline();
-                } else {
-                    doc += self.arena.space();
+                if let Some((_, spaces)) = child.text().rsplit_once('\n') {
+                    indent = Some(spaces);
+                    continue;
                 }
-            }
+                self.arena.space()
+            } else {
+                continue;
+            };
+            doc += match indent.take() {
+                Some(indent) => self.raw_line(raw_indent, indent, child_doc),
+                None => child_doc,
+            };
         }
-        doc
+        doc.align()
+    }
+
+    /// Convert a raw block with the formatter registered for its language, if any.
+    fn try_convert_raw_with_formatter(&'a self, raw: Raw<'a>) -> Option<ArenaDoc<'a>> {
+        let lang = raw.lang()?;
+        let formatter = self.raw_formatters.get(lang.get())?;
+        if !raw.block() {
+            return None;
+        }
+        let body = raw.lines().map(|line| line.get().as_str()).join("\n");
+        let formatted = formatter(&body)?;
+
+        let fence = get_raw_fence(formatted.lines());
+        // Body lines are indented as the closing delimiter, so that they are dedented exactly.
+        let indent = raw
+            .to_untyped()
+            .children()
+            .rev()
+            .find(|child| child.kind() == SyntaxKind::RawTrimmed)
+            .and_then(|child| child.text().rsplit_once('\n'))
+            .map_or("", |(_, indent)| indent);
+
+        let raw_indent = self.attr_store.raw_indent(raw.to_untyped());
+        let mut doc = self.arena.text(fence.clone()) + self.convert_verbatim(lang);
+        for line in formatted.lines() {
+            doc += self.raw_line(
+                raw_indent,
+                indent,
+                keep_trailing_whitespace(self.arena.text(line.to_string()), line),
+            );
+        }
+        doc += self.raw_line(raw_indent, indent, self.arena.text(fence));
+        Some(doc.align())
+    }
+
+    /// A line in a raw block, put after a line break and the given indentation.
+    ///
+    /// Lines in raw blocks are literal, regardless of the nesting of the surrounding content.
+    /// If the block starts a line at `raw_indent` in the source, the lines are shifted as much as
+    /// the opening fence, so that they keep their indentation relative to it. Otherwise they keep
+    /// their original indentation. The block must be aligned, so that the nesting is the column
+    /// of the fence. The line is nested together with the line break, as a line break takes the
+    /// indentation of what follows it.
+    fn raw_line(
+        &'a self,
+        raw_indent: Option<usize>,
+        indent: &'a str,
+        line: ArenaDoc<'a>,
+    ) -> ArenaDoc<'a> {
+        let line = line.into_doc();
+        self.arena.nesting(move |nesting| {
+            let raw_indent = raw_indent.unwrap_or(nesting);
+            let indent = match nesting.checked_sub(raw_indent) {
+                Some(shift) => self.arena.text(" ".repeat(shift)) + indent,
+                None => {
+                    let dedent = raw_indent - nesting;
+                    let start = indent
+                        .char_indices()
+                        .nth(dedent)
+                        .map_or(indent.len(), |(i, _)| i);
+                    self.arena.text(&indent[start..])
+                }
+            };
+            (self.arena.hardline() + indent + line)
+                .nest(-(nesting as isize))
+                .into_doc()
+        })
     }
 
     fn convert_ref(&'a self, reference: Ref<'a>) -> ArenaDoc<'a> {
@@ -224,10 +473,19 @@ impl<'a> PrettyPrinter<'a> {
             return res;
         }
 
-        let _g = self.with_mode(Mode::Math);
-        let body = self.convert_math(equation.body());
+        let _g = self.with_mode(if equation.block() {
+            Mode::Math
+        } else {
+            Mode::MathInline
+        });
+        let is_multi_line = self.attr_store.is_multiline(equation.to_untyped());
+        let body = if equation.block() && is_multi_line && self.config.align_math {
+            self.try_convert_aligned_math(equation.body())
+        } else {
+            None
+        }
+        .unwrap_or_else(|| self.convert_math(equation.body()));
         let doc = if equation.block() {
-            let is_multi_line = self.attr_store.is_multiline(equation.to_untyped());
             if is_multi_line {
                 (self.arena.hardline() + body).nest(self.config.tab_spaces as isize)
                     + self.arena.hardline()
@@ -246,9 +504,54 @@ impl<'a> PrettyPrinter<'a> {
         if let Some(res) = self.check_disabled(math.to_untyped()) {
             return res;
         }
+        self.convert_math_children(math.to_untyped().children().as_slice())
+    }
+
+    fn convert_math_children(&'a self, children: &'a [SyntaxNode]) -> ArenaDoc<'a> {
         let mut doc = self.arena.nil();
-        for node in math.to_untyped().children() {
-            if let Some(expr) = node.cast::<Expr>() {
+        let mut prev_is_spaced_operator = false;
+        for (i, node) in children.iter().enumerate() {
+            let next = children.get(i + 1);
+            let is_spaced_operator = self.config.math_operator_spacing
+                && !self.attr_store.is_format_disabled(node)
+                && util::is_math_binary_operator(node, &children[..i]);
+            if i > 0
+                && !is_spaced_operator
+                && !prev_is_spaced_operator
+                && self.needs_math_separator(&children[i - 1], node)
+            {
+                doc += self.arena.space();
+            }
+            prev_is_spaced_operator = is_spaced_operator;
+            if let Some(attach) = node
+                .cast::<MathAttach>()
+                .filter(|_| self.config.strip_attach_parens)
+            {
+                // The parentheses of the last script can be removed only if nothing sticks to it.
+                let is_followed = next.is_some_and(|next| next.kind() != SyntaxKind::Space);
+                doc += self
+                    .check_disabled(node)
+                    .or_else(|| self.check_unformattable(node))
+                    .unwrap_or_else(|| self.convert_math_attach_impl(attach, !is_followed));
+            } else if is_spaced_operator {
+                // Alignment points stick to operators, like `&=`.
+                let needs_space = |sibling: &SyntaxNode| {
+                    !matches!(
+                        sibling.kind(),
+                        SyntaxKind::Space | SyntaxKind::MathAlignPoint
+                    )
+                };
+                if i > 0 && needs_space(&children[i - 1]) {
+                    doc += self.arena.space();
+                }
+                doc += match node.cast::<MathShorthand>() {
+                    Some(shorthand) => self.convert_math_shorthand(shorthand),
+                    None => self.convert_verbatim_untyped(node),
+                };
+                if next.is_some_and(needs_space) {
+                    doc += self.arena.space();
+                }
+            } else if let Some(expr) = node.cast::<Expr>() {
                 let expr_doc = self.convert_expr(expr);
                 doc += expr_doc;
             } else if let Some(space) = node.cast::<Space>() {
@@ -260,6 +563,59 @@ impl<'a> PrettyPrinter<'a> {
         doc
     }
 
+    /// Whether a space is needed between two adjacent nodes in math, so that a named symbol does
+    /// not merge with its neighbors, e.g., `a arrow.r b` rather than `aarrow.rb`.
+    fn needs_math_separator(&self, left: &SyntaxNode, right: &SyntaxNode) -> bool {
+        if self.config.math_symbol_style != MathSymbolStyle::Named {
+            return false;
+        }
+        let (Some((left, left_named)), Some((right, right_named))) =
+            (self.math_edge(left, true), self.math_edge(right, false))
+        else {
+            return false;
+        };
+        (left_named || right_named)
+            && left.is_alphanumeric()
+            && (right.is_alphanumeric() || matches!(right, '(' | '.'))
+    }
+
+    /// Get the last or first printed character of a node in math, and whether it belongs to a
+    /// named symbol rewritten from a shorthand.
+    fn math_edge(&self, node: &SyntaxNode, last: bool) -> Option<(char, bool)> {
+        let mut leaf = node;
+        while let Some(child) = if last {
+            leaf.children().last()
+        } else {
+            leaf.children().next()
+        } {
+            leaf = child;
+        }
+        let named = if self.attr_store.is_format_disabled(node)
+            || self.attr_store.is_format_disabled(leaf)
+        {
+            None
+        } else {
+            util::restyle_math_shorthand(leaf, self.config.math_symbol_style)
+        };
+        let text = named.unwrap_or(leaf.text().as_str());
+        let edge = if last {
+            text.chars().next_back()
+        } else {
+            text.chars().next()
+        };
+        edge.map(|edge| (edge, named.is_some()))
+    }
+
+    fn convert_math_shorthand(&'a self, math_shorthand: MathShorthand<'a>) -> ArenaDoc<'a> {
+        match util::restyle_math_shorthand(
+            math_shorthand.to_untyped(),
+            self.config.math_symbol_style,
+        ) {
+            Some(text) => self.arena.text(text),
+            None => self.convert_verbatim(math_shorthand),
+        }
+    }
+
     fn convert_ident(&'a self, ident: Ident<'a>) -> ArenaDoc<'a> {
         self.convert_verbatim(ident)
     }
@@ -336,10 +692,25 @@ impl<'a> PrettyPrinter<'a> {
             }
             (has_space_before_math, has_space_after_math)
         }
+        /// Spaces are normalized only inside non-empty parentheses and brackets.
+        fn can_normalize_spaces(math_delimited: MathDelimited<'_>) -> bool {
+            let open = math_delimited.open().to_untyped().text();
+            let close = math_delimited.close().to_untyped().text();
+            matches!((open.as_str(), close.as_str()), ("(", ")") | ("[", "]"))
+                && math_delimited.body().exprs().next().is_some()
+        }
         let open = self.convert_expr(math_delimited.open());
         let close = self.convert_expr(math_delimited.close());
         let body = self.convert_math(math_delimited.body());
-        let (has_space_before_math, has_space_after_math) = has_spaces(math_delimited);
+        let (has_space_before_math, has_space_after_math) = match self.config.math_delim_spacing {
+            MathDelimSpacing::Spaced | MathDelimSpacing::Tight
+                if can_normalize_spaces(math_delimited) =>
+            {
+                let spaced = self.config.math_delim_spacing == MathDelimSpacing::Spaced;
+                (spaced, spaced)
+            }
+            _ => has_spaces(math_delimited),
+        };
 
         body.enclose(
             if has_space_before_math {
@@ -358,6 +729,14 @@ impl<'a> PrettyPrinter<'a> {
     }
 
     fn convert_math_attach(&'a self, math_attach: MathAttach<'a>) -> ArenaDoc<'a> {
+        self.convert_math_attach_impl(math_attach, false)
+    }
+
+    fn convert_math_attach_impl(
+        &'a self,
+        math_attach: MathAttach<'a>,
+        can_strip_last: bool,
+    ) -> ArenaDoc<'a> {
         let mut doc = self.convert_expr(math_attach.base());
         let prime_index = math_attach
             .to_untyped()
@@ -384,21 +763,33 @@ impl<'a> PrettyPrinter<'a> {
             .find_map(|(i, n)| SyntaxNode::cast::<Expr<'_>>(n).map(|n| (i, n)))
             .map(|(i, _n)| i);
 
-        #[derive(Debug)]
+        #[derive(Debug, PartialEq, Eq)]
         enum IndexType {
             Prime,
             Bottom,
             Top,
         }
 
+        // Primes always come first, as they can only follow the base directly.
         let mut index_types = [IndexType::Prime, IndexType::Bottom, IndexType::Top];
-        index_types.sort_by_key(|index_type| match index_type {
-            IndexType::Prime => prime_index,
-            IndexType::Bottom => bottom_index,
-            IndexType::Top => top_index,
-        });
+        if !self.config.sort_math_attachments {
+            index_types.sort_by_key(|index_type| match index_type {
+                IndexType::Prime => prime_index,
+                IndexType::Bottom => bottom_index,
+                IndexType::Top => top_index,
+            });
+        }
 
-        for index in index_types {
+        let last_script = index_types
+            .iter()
+            .rev()
+            .find(|index_type| match index_type {
+                IndexType::Prime => false,
+                IndexType::Bottom => bottom_index.is_some(),
+                IndexType::Top => top_index.is_some(),
+            });
+        for index in &index_types {
+            let can_strip = can_strip_last || last_script != Some(index);
             match index {
                 IndexType::Prime => {
                     if let Some(primes) = math_attach.primes() {
@@ -407,12 +798,12 @@ impl<'a> PrettyPrinter<'a> {
                 }
                 IndexType::Bottom => {
                     if let Some(bottom) = math_attach.bottom() {
-                        doc += self.arena.text("_") + self.convert_expr(bottom);
+                        doc += self.arena.text("_") + self.convert_math_script(bottom, can_strip);
                     }
                 }
                 IndexType::Top => {
                     if let Some(top) = math_attach.top() {
-                        doc += self.arena.text("^") + self.convert_expr(top);
+                        doc += self.arena.text("^") + self.convert_math_script(top, can_strip);
                     }
                 }
             }
@@ -420,37 +811,107 @@ impl<'a> PrettyPrinter<'a> {
         doc
     }
 
+    fn convert_math_script(&'a self, script: Expr<'a>, can_strip: bool) -> ArenaDoc<'a> {
+        if can_strip && self.config.strip_attach_parens {
+            if let Some(atom) = util::get_parenthesized_math_atom(script) {
+                return self.convert_expr(atom);
+            }
+        }
+        self.convert_expr(script)
+    }
+
     fn convert_math_primes(&'a self, math_primes: MathPrimes<'a>) -> ArenaDoc<'a> {
         self.arena.text("'".repeat(math_primes.count()))
     }
 
     fn convert_math_frac(&'a self, math_frac: MathFrac<'a>) -> ArenaDoc<'a> {
-        let singleline = self.convert_expr(math_frac.num())
-            + self.arena.space()
-            + self.arena.text("/")
-            + self.arena.space()
-            + self.convert_expr(math_frac.denom());
-        // TODO: add multiline version
-        singleline
+        let num = self.convert_expr(math_frac.num());
+        let denom = self.convert_expr(math_frac.denom());
+        // Break after the slash only if the fraction cannot fit on a line by itself, so that
+        // short fractions are not split when something else overflows the line.
+        // Fractions in inline equations are never broken.
+        let breakable = self.current_mode() != Mode::MathInline
+            && !self.frac_fits_in_line(num.clone(), denom.clone());
+        if !breakable {
+            return num + self.arena.text(" / ") + denom;
+        }
+        (num + self.arena.text(" /")
+            + (self.arena.line() + denom).nest(self.config.tab_spaces as isize))
+        .group()
+    }
+
+    /// Whether a fraction fits on one line of the full width.
+    fn frac_fits_in_line(&'a self, num: ArenaDoc<'a>, denom: ArenaDoc<'a>) -> bool {
+        let width = self.config.render_width();
+        let text = (num + self.arena.text(" / ") + denom)
+            .pretty(width)
+            .to_string();
+        !text.contains('\n') && text.chars().count() <= width
     }
 
     fn convert_math_root(&'a self, math_root: MathRoot<'a>) -> ArenaDoc<'a> {
-        let sqrt_sym = if let Some(index) = math_root.index() {
-            if index == 3 {
-                "∛"
-            } else if index == 4 {
-                "∜"
-            } else {
-                // TODO: actually unreachable
-                "√"
-            }
-        } else {
-            "√"
+        // The root sign determines the index, so we keep it as is.
+        // Other indices can only be written as `root(n, x)`, which is a function call.
+        let sqrt_sym = match math_root.to_untyped().children().next() {
+            Some(sign) if sign.kind() == SyntaxKind::Root => sign.text().as_str(),
+            _ => "√",
         };
         self.arena.text(sqrt_sym) + self.convert_expr(math_root.radicand())
     }
 }
 
+/// Get the shortest fence of a raw block, which is longer than any run of backticks in its lines.
+fn get_raw_fence<'s>(lines: impl Iterator<Item = &'s str>) -> String {
+    let longest_run = lines
+        .flat_map(|line| line.split(|c| c != '`').map(str::len))
+        .max()
+        .unwrap_or(0);
+    "`".repeat((longest_run + 1).max(3))
+}
+
+/// Keep the trailing whitespace of a line in a raw block, which is meaningful.
+fn keep_trailing_whitespace<'a>(doc: ArenaDoc<'a>, line: &str) -> ArenaDoc<'a> {
+    if line.ends_with(char::is_whitespace) {
+        doc.annotate(Annotation::KeepTrailingWhitespace)
+    } else {
+        doc
+    }
+}
+
+/// Rendered text split into pieces by the innermost annotation over them.
+#[derive(Default)]
+struct AnnotatedText {
+    pieces: Vec<(String, Option<Annotation>)>,
+    annotations: Vec<Annotation>,
+}
+
+impl Render for AnnotatedText {
+    type Error = ();
+
+    fn write_str(&mut self, s: &str) -> Result<usize, ()> {
+        let annotation = self.annotations.last().copied();
+        match self.pieces.last_mut() {
+            Some((text, last)) if *last == annotation => text.push_str(s),
+            _ => self.pieces.push((s.to_string(), annotation)),
+        }
+        Ok(s.len())
+    }
+
+    fn fail_doc(&self) {}
+}
+
+impl RenderAnnotated<'_, Annotation> for AnnotatedText {
+    fn push_annotation(&mut self, annotation: &Annotation) -> Result<(), ()> {
+        self.annotations.push(*annotation);
+        Ok(())
+    }
+
+    fn pop_annotation(&mut self) -> Result<(), ()> {
+        self.annotations.pop();
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StripMode {
     None,
@@ -458,15 +919,19 @@ pub enum StripMode {
     PrefixOnBoundaryMarkers,
 }
 
-fn trivia<'a>(arena: &'a Arena<'a>, node: &'a SyntaxNode) -> ArenaDoc<'a> {
+fn trivia<'a>(arena: &'a Arena<'a, Annotation>, node: &'a SyntaxNode) -> ArenaDoc<'a> {
     to_doc(arena, node.text(), StripMode::None)
 }
 
-fn trivia_strip_prefix<'a>(arena: &'a Arena<'a>, node: &'a SyntaxNode) -> ArenaDoc<'a> {
+fn trivia_strip_prefix<'a>(arena: &'a Arena<'a, Annotation>, node: &'a SyntaxNode) -> ArenaDoc<'a> {
     to_doc(arena, node.text(), StripMode::Prefix)
 }
 
-pub fn to_doc<'a>(arena: &'a Arena<'a>, s: &'a str, strip_prefix: StripMode) -> ArenaDoc<'a> {
+pub fn to_doc<'a>(
+    arena: &'a Arena<'a, Annotation>,
+    s: &'a str,
+    strip_prefix: StripMode,
+) -> ArenaDoc<'a> {
     let get_line = |i: itertools::Position, line: &'a str| -> &'a str {
         let should_trim = matches!(strip_prefix, StripMode::Prefix)
             || (matches!(strip_prefix, StripMode::PrefixOnBoundaryMarkers)
@@ -497,3 +962,258 @@ pub fn to_doc<'a>(arena: &'a Arena<'a>, s: &'a str, strip_prefix: StripMode) ->
         res
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::format, Config, MathDelimSpacing, MathSymbolStyle, QuoteStyle};
+
+    #[test]
+    fn test_quote_style() {
+        let config = Config::new().with_quote_style(QuoteStyle::Normalize);
+        assert_eq!(
+            format(r#"#"\u{41}\u{1F600}c""#, config.clone()),
+            "#\"A\u{1F600}c\"\n"
+        );
+        assert_eq!(
+            format(r#"#"\u{41}\u{1f600}c""#, Config::new()),
+            "#\"\\u{41}\\u{1f600}c\"\n"
+        );
+        // Escapes that are necessary are kept.
+        let content = r#"#"\u{22}\u{5c}\u{9}\n\t\"\\""#.to_string() + "\n";
+        assert_eq!(format(&content, config.clone()), content);
+        // So are escapes of invisible characters.
+        let content = r#"#"\u{200B}\u{ad}\u{200d}\u{202e}\u{2060}\u{fe0f}\u{feff}\u{e0001}""#
+            .to_string()
+            + "\n";
+        assert_eq!(format(&content, config.clone()), content);
+        // Multiline strings are untouched.
+        let content = "#\"\\u{41}\n\\u{42}\"\n";
+        assert_eq!(format(content, config), content);
+    }
+
+    #[test]
+    fn test_raw_trailing_whitespace() {
+        let content = "```md\nfoo  \nbar\n```\n";
+        assert_eq!(format(content, Config::new()), content);
+        let content = "```md\nfoo  \n```  \n";
+        assert_eq!(format(content, Config::new()), "```md\nfoo  \n```\n");
+        // Raw blocks rendered with their own width keep it too.
+        let content = "#f(```md\nfoo  \n```)\n";
+        assert_eq!(format(content, Config::new().with_code_width(10)), content);
+
+        // Documents rendered without post-processing have no marks in the text.
+        let source = typst_syntax::Source::detached("```md\nfoo  \nbar\n```");
+        let attr_store = crate::AttrStore::new(source.root());
+        let printer = super::PrettyPrinter::new(Config::new(), &attr_store);
+        let doc = printer.convert_markup(source.root().cast().unwrap());
+        assert_eq!(doc.pretty(80).to_string(), source.text());
+    }
+
+    #[test]
+    fn test_raw_fence() {
+        let content = "`````md\n# Title\n`````\n";
+        assert_eq!(format(content, Config::new()), "```md\n# Title\n```\n");
+        let content = "`````md\nSome ``code``.\n`````\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "```md\nSome ``code``.\n```\n"
+        );
+        let content = "`````md\n```rs\nlet x = 1;\n```\n`````\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "````md\n```rs\nlet x = 1;\n```\n````\n"
+        );
+        // Single-line raws are not blocks, so their fences are kept.
+        let content = "#(````` ``x`` `````)\n";
+        assert_eq!(format(content, Config::new()), content);
+    }
+
+    #[test]
+    fn test_math_root() {
+        let content = "$√x + ∛y + ∜(x + y) + root(5, x) + root(n, x + y)$\n";
+        assert_eq!(format(content, Config::new()), content);
+    }
+
+    #[test]
+    fn test_strip_attach_parens() {
+        let content = "$x^(2) + x_(i)^(n) + x^(a b) + x'_(0) + x^(2)3 + e^(alpha) y_(12)$\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_strip_attach_parens(true)),
+            "$x^2 + x_i^n + x^(a b) + x'_0 + x^(2)3 + e^alpha y_12$\n"
+        );
+    }
+
+    #[test]
+    fn test_math_operator_spacing() {
+        let content = "$a+b=c-(-d)<=x_(n+1), -y$\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, Config::new().with_math_operator_spacing(true)),
+            "$a + b = c - (-d) <= x_(n + 1), -y$\n"
+        );
+        let content = "$f(a+b) + x^+ &= -1 \\\n  &= + 1$\n";
+        assert_eq!(
+            format(content, Config::new().with_math_operator_spacing(true)),
+            "$f(a + b) + x^+ &= -1 \\\n  &= + 1$\n"
+        );
+    }
+
+    #[test]
+    fn test_math_align() {
+        let config = Config::new().with_align_math(true);
+        let content = "$\n  a &= b + c \\\n  d + e &=f \\\n  &= g\n$\n";
+        assert_eq!(format(content, Config::new()), content);
+        assert_eq!(
+            format(content, config.clone()),
+            "$\n  a     &= b + c \\\n  d + e &=f \\\n        &= g\n$\n"
+        );
+        let content = "$\n  a & b & c \\\n  dd & e\n$\n";
+        assert_eq!(
+            format(content, config.clone()),
+            "$\n  a  & b & c \\\n  dd & e\n$\n"
+        );
+        // Rows spanning multiple lines are not aligned.
+        let content = "$\n  a &= b\n  + c \\\n  d + e &= f\n$\n";
+        assert_eq!(format(content, config), content);
+    }
+
+    #[test]
+    fn test_math_frac() {
+        let config = Config::new().with_width(40);
+        assert_eq!(
+            format("$ (a + b + c + d + e + f)/(x + y + z) $", config.clone()),
+            "$\n  (a + b + c + d + e + f) / (x + y + z)\n$\n"
+        );
+        assert_eq!(
+            format(
+                "$ (a + b + c + d + e + f + g + h + i + j + k)/(x + y) $",
+                config.clone()
+            ),
+            "$\n  (a + b + c + d + e + f + g + h + i + j + k) /\n    (x + y)\n$\n"
+        );
+        assert_eq!(
+            format(
+                "$ (a + b + c + d + e + f + g + h + i + j + k)/(x + y) $",
+                config.with_tab_spaces(4)
+            ),
+            "$\n    (a + b + c + d + e + f + g + h + i + j + k) /\n        (x + y)\n$\n"
+        );
+    }
+
+    #[test]
+    fn test_ref_supplement() {
+        for content in [
+            "@fig[#strong[Figure]]\n",
+            "@fig[*Fig.* _one_ #emph[two]]\n",
+            "@fig[]\n",
+        ] {
+            assert_eq!(format(content, Config::new()), content);
+        }
+        assert_eq!(
+            format("@fig[*Fig.*   #strong[A]]\n", Config::new()),
+            "@fig[*Fig.* #strong[A]]\n"
+        );
+        assert_eq!(
+            format(
+                "@fig[ ]\n",
+                Config::new().with_collapse_empty_content_blocks(true)
+            ),
+            "@fig[]\n"
+        );
+    }
+
+    #[test]
+    fn test_math_delim_spacing() {
+        let content = "$( x ) + (y) + [ z] + { w }$\n";
+        assert_eq!(format(content, Config::new()), content);
+        let spaced = Config::new().with_math_delim_spacing(MathDelimSpacing::Spaced);
+        assert_eq!(
+            format(content, spaced.clone()),
+            "$( x ) + ( y ) + [ z ] + { w }$\n"
+        );
+        let tight = Config::new().with_math_delim_spacing(MathDelimSpacing::Tight);
+        assert_eq!(
+            format(content, tight.clone()),
+            "$(x) + (y) + [z] + { w }$\n"
+        );
+        assert_eq!(format("$f()$\n", spaced), "$f()$\n");
+        assert_eq!(format("$( x (y) )$\n", tight), "$(x (y))$\n");
+    }
+
+    #[test]
+    fn test_sort_math_attachments() {
+        let content = "$x^2_i + x'^2_i + x_i^2 + x'_i^2 + x^2 + x'_i$\n";
+        assert_eq!(format(content, Config::new()), content);
+        let config = Config::new().with_sort_math_attachments(true);
+        assert_eq!(
+            format(content, config.clone()),
+            "$x_i^2 + x'_i^2 + x_i^2 + x'_i^2 + x^2 + x'_i$\n"
+        );
+        assert_eq!(
+            format("$x^(2)_(i)$\n", config.with_strip_attach_parens(true)),
+            "$x_i^2$\n"
+        );
+    }
+
+    #[test]
+    fn test_math_symbol_style() {
+        let content = "$a -> b, x<=y, f(x)->(y), [| x |]$\n`->`\n";
+        assert_eq!(format(content, Config::new()), content);
+        let unicode = Config::new().with_math_symbol_style(MathSymbolStyle::Unicode);
+        assert_eq!(
+            format(content, unicode),
+            "$a → b, x≤y, f(x)→(y), [| x |]$\n`->`\n"
+        );
+        let named = Config::new().with_math_symbol_style(MathSymbolStyle::Named);
+        assert_eq!(
+            format(content, named.clone()),
+            "$a arrow.r b, x lt.eq y, f(x)arrow.r (y), [| x |]$\n`->`\n"
+        );
+        let content = "// @typstyle off\n$a->b$\n";
+        assert_eq!(format(content, named), content);
+    }
+
+    #[test]
+    fn test_normalize_numbers() {
+        let config = Config::new().with_normalize_numbers(true);
+        for (number, expected) in [
+            ("007", "7"),
+            ("000", "0"),
+            ("0x0F", "0x0F"),
+            ("0o017", "0o017"),
+            ("0b01", "0b01"),
+            ("00.50", "0.50"),
+            ("0.5", "0.5"),
+            ("1e10", "1e10"),
+            ("007.5e3", "7.5e3"),
+            ("050%", "50%"),
+            ("010pt", "10pt"),
+            ("03mm", "3mm"),
+            ("002.5cm", "2.5cm"),
+            ("04in", "4in"),
+            ("090deg", "90deg"),
+            ("01rad", "1rad"),
+            ("01.5em", "1.5em"),
+            ("02fr", "2fr"),
+        ] {
+            let content = format!("#let x = {number}\n");
+            assert_eq!(format(&content, Config::new()), content);
+            assert_eq!(
+                format(&content, config.clone()),
+                format!("#let x = {expected}\n")
+            );
+        }
+    }
+
+    #[test]
+    fn test_collapse_spaces_in_code_and_math() {
+        assert_eq!(format("#(1  +  2)\n", Config::new()), "#(1 + 2)\n");
+        assert_eq!(
+            format("#let x = 1  +  2\n", Config::new()),
+            "#let x = 1 + 2\n"
+        );
+        assert_eq!(format("$1  +  2$\n", Config::new()), "$1 + 2$\n");
+        assert_eq!(format("$ 1  +  2 $\n", Config::new()), "$ 1 + 2 $\n");
+    }
+}
@@ -9,6 +9,8 @@ pub enum Mode {
     /// Corresponds to [`typst_syntax::parser::NewlineMode::Continue`].
     CodeCont,
     Math,
+    /// The math mode of an inline equation, which should never span multiple lines.
+    MathInline,
 }
 
 #[allow(unused)]
@@ -26,8 +28,9 @@ impl Mode {
         self == Self::CodeCont
     }
 
+    /// Returns `true` if the current mode is either `Math` or `MathInline`.
     pub fn is_math(self) -> bool {
-        self == Self::Math
+        self == Self::Math || self == Self::MathInline
     }
 }
 
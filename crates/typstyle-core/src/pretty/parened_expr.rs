@@ -3,7 +3,7 @@ use typst_syntax::ast::*;
 
 use crate::PrettyPrinter;
 
-use super::{mode::Mode, util::has_comment_children, ArenaDoc};
+use super::{mode::Mode, util::has_comment_children, Annotation, ArenaDoc};
 
 impl<'a> PrettyPrinter<'a> {
     /// We do not care whether it is `Pattern` or `Expr`.
@@ -26,6 +26,35 @@ impl<'a> PrettyPrinter<'a> {
         self.convert_parenthesized_impl(parenthesized)
     }
 
+    /// Convert a parenthesized expression.
+    /// If `remove_redundant_parens` is enabled, parentheses around atomic expressions in code
+    /// are removed, except for expressions embedded in markup, e.g., `#(x)text`. See
+    /// [`Self::convert_embedded_expr`].
+    pub(super) fn convert_parenthesized_expr(
+        &'a self,
+        parenthesized: Parenthesized<'a>,
+    ) -> ArenaDoc<'a> {
+        if self.config.remove_redundant_parens
+            && !self.current_mode().is_math()
+            && !has_comment_children(parenthesized.to_untyped())
+        {
+            match parenthesized.expr() {
+                Expr::Parenthesized(paren) => return self.convert_parenthesized_expr(paren),
+                expr if is_atomic(expr) => return self.convert_expr(expr),
+                _ => {}
+            }
+        }
+        self.convert_parenthesized(parenthesized)
+    }
+
+    /// Convert an expression embedded in markup, which keeps its parentheses.
+    pub(crate) fn convert_embedded_expr(&'a self, expr: Expr<'a>) -> ArenaDoc<'a> {
+        self.convert_expr_with(expr, |this, expr| match expr {
+            Expr::Parenthesized(paren) => this.convert_parenthesized(paren),
+            expr => this.convert_expr_impl(expr),
+        })
+    }
+
     /// Convert an expression with optional parentheses.
     /// If the expression is a parenthesized expression, a code block, a content block, or a function call,
     /// the expression will be converted without parentheses.
@@ -57,12 +86,34 @@ impl<'a> PrettyPrinter<'a> {
 }
 
 /// Wrap the body with parentheses if the body is layouted on multiple lines.
-fn optional_paren<'a>(arena: &'a Arena<'a>, body: ArenaDoc<'a>, indent: usize) -> ArenaDoc<'a> {
+fn optional_paren<'a>(
+    arena: &'a Arena<'a, Annotation>,
+    body: ArenaDoc<'a>,
+    indent: usize,
+) -> ArenaDoc<'a> {
     let open = (arena.text("(") + arena.hardline()).flat_alt(arena.nil());
     let close = (arena.hardline() + arena.text(")")).flat_alt(arena.nil());
     ((open + body).nest(indent as isize) + close).group()
 }
 
+/// Checks if an expression is atomic, so that parentheses around it are redundant in code.
+///
+/// Numbers are excluded, as `(1).abs()` cannot be written as `1.abs()`.
+fn is_atomic(expr: Expr<'_>) -> bool {
+    matches!(
+        expr,
+        Expr::Ident(_)
+            | Expr::None(_)
+            | Expr::Auto(_)
+            | Expr::Bool(_)
+            | Expr::Str(_)
+            | Expr::Code(_)
+            | Expr::Content(_)
+            | Expr::Array(_)
+            | Expr::Dict(_)
+    )
+}
+
 /// Checks if parentheses are needed for an expression that may span multiple lines.
 fn is_paren_needed(expr: Expr<'_>) -> bool {
     !matches!(
@@ -81,3 +132,33 @@ fn is_paren_needed(expr: Expr<'_>) -> bool {
             | Expr::Raw(_)
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::format, Config};
+
+    #[test]
+    fn test_remove_redundant_parens() {
+        let config = Config::new().with_remove_redundant_parens(true);
+        let content = "#{\n  let y = (x)\n  let z = ((a + b))\n  f((\"s\"), ([a]), (1))\n}\n";
+        assert_eq!(
+            format(content, config.clone()),
+            "#{\n  let y = x\n  let z = (a + b)\n  f(\"s\", [a], 1)\n}\n"
+        );
+        assert_eq!(
+            format(content, Config::new()),
+            "#{\n  let y = (x)\n  let z = (a + b)\n  f(\"s\", [a], 1)\n}\n"
+        );
+
+        // Parentheses in code embedded in markup are removed as well.
+        let content = "#let x = (a)\n#[#let y = ((b))]\n";
+        assert_eq!(
+            format(content, config.clone()),
+            "#let x = a\n#[#let y = b]\n"
+        );
+
+        // Parentheses in markup, around keys, and in patterns are kept.
+        let content = "#(x)text\n#[#(y)text]\n#{\n  let d = ((x): 1)\n  let (a) = 1\n}\n";
+        assert_eq!(format(content, config), content);
+    }
+}
@@ -7,4 +7,6 @@ pub enum FoldStyle {
     Never,
     /// Always fold items
     Always,
+    /// Pack as many items in each line as fit, and break lines between items otherwise
+    Fill,
 }
@@ -74,19 +74,43 @@ impl<'a> PrettyPrinter<'a> {
             }
             table
         };
+        let table: Vec<Vec<ArenaDoc<'a>>> = table
+            .into_iter()
+            .map(|row| {
+                row.cells
+                    .into_iter()
+                    .map(|cell| self.convert_arg(cell))
+                    .collect()
+            })
+            .collect();
+        let mut paddings = if self.config.align_table_cells {
+            get_cell_paddings(&table, self.config.render_width()).map(Vec::into_iter)
+        } else {
+            None
+        };
         for (row_pos, row) in table.into_iter().with_position() {
             let mut row_doc = self.arena.nil();
-            for (pos, cell) in row.cells.into_iter().with_position() {
-                row_doc = row_doc
-                    + self.convert_arg(cell)
-                    + self.arena.text(",")
-                    + (if has_predecessor(&pos) {
-                        self.arena.line()
-                    } else if has_predecessor(&row_pos) {
-                        self.arena.line_()
-                    } else {
-                        self.arena.nil()
-                    });
+            if let Some(row_paddings) = paddings.as_mut().and_then(Iterator::next) {
+                // Aligned cells are kept in one line.
+                for ((pos, cell), padding) in row.into_iter().with_position().zip(row_paddings) {
+                    row_doc += cell + self.arena.text(",");
+                    if has_predecessor(&pos) {
+                        row_doc += self.arena.text(" ".repeat(padding));
+                    }
+                }
+            } else {
+                for (pos, cell) in row.into_iter().with_position() {
+                    row_doc = row_doc
+                        + cell
+                        + self.arena.text(",")
+                        + (if has_predecessor(&pos) {
+                            self.arena.line()
+                        } else if has_predecessor(&row_pos) {
+                            self.arena.line_()
+                        } else {
+                            self.arena.nil()
+                        });
+                }
             }
             doc += row_doc.group()
                 + (if has_predecessor(&row_pos) {
@@ -99,8 +123,61 @@ impl<'a> PrettyPrinter<'a> {
     }
 }
 
-pub fn is_table(node: FuncCall<'_>) -> bool {
-    indent_func_name(node) == Some("table") || indent_func_name(node) == Some("grid")
+/// Computes the number of spaces after each cell, so that the cells of each column start at the
+/// same column. Returns `None` if any cell spans multiple lines.
+pub(super) fn get_cell_paddings(
+    table: &[Vec<ArenaDoc<'_>>],
+    max_width: usize,
+) -> Option<Vec<Vec<usize>>> {
+    let mut cell_widths = Vec::with_capacity(table.len());
+    for row in table {
+        let mut row_widths = Vec::with_capacity(row.len());
+        for cell in row {
+            let text = cell.pretty(max_width).to_string();
+            if text.contains('\n') {
+                return None;
+            }
+            row_widths.push(text.chars().count());
+        }
+        cell_widths.push(row_widths);
+    }
+
+    // The last cell of each row is not padded, so it does not affect the column widths.
+    let mut column_widths: Vec<usize> = vec![];
+    for row in &cell_widths {
+        for (i, &width) in row.iter().take(row.len().saturating_sub(1)).enumerate() {
+            if i < column_widths.len() {
+                column_widths[i] = column_widths[i].max(width);
+            } else {
+                column_widths.push(width);
+            }
+        }
+    }
+
+    let paddings = cell_widths
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .enumerate()
+                .map(|(i, width)| column_widths.get(i).map_or(0, |&max| max - width) + 1)
+                .collect()
+        })
+        .collect();
+    Some(paddings)
+}
+
+/// Checks whether the function call is a `table` or `grid`, or one of the given table-like
+/// functions.
+pub fn is_table(node: FuncCall<'_>, extra_tables: &[String]) -> bool {
+    if indent_func_name(node) == Some("table") || indent_func_name(node) == Some("grid") {
+        return true;
+    }
+    let name = match node.callee() {
+        Expr::Ident(ident) => ident.as_str(),
+        Expr::FieldAccess(access) => access.field().as_str(),
+        _ => return false,
+    };
+    extra_tables.iter().any(|extra| extra == name)
 }
 
 fn is_formatable(node: FuncCall<'_>) -> bool {
@@ -145,7 +222,8 @@ fn get_table_columns(node: FuncCall<'_>) -> Option<usize> {
         if let Arg::Named(name) = node {
             if name.name().as_str() == "columns" {
                 if let Some(count) = name.expr().to_untyped().cast::<Int>() {
-                    return Some(count.get() as usize);
+                    // `columns: 0` is invalid in typst; leave such tables alone.
+                    return usize::try_from(count.get()).ok().filter(|&n| n > 0);
                 }
                 if let Some(arr) = name.expr().to_untyped().cast::<Array>() {
                     return Some(arr.items().count());
@@ -158,10 +236,59 @@ fn get_table_columns(node: FuncCall<'_>) -> Option<usize> {
 
 /// Returns the number of columns in the table if the table is formatable.
 /// Otherwise, returns None.
-pub(super) fn is_formatable_table(node: FuncCall<'_>) -> Option<usize> {
-    if is_table(node) && is_formatable(node) {
+pub(super) fn is_formatable_table(node: FuncCall<'_>, extra_tables: &[String]) -> Option<usize> {
+    if is_table(node, extra_tables) && is_formatable(node) {
         get_table_columns(node)
     } else {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::format, Config};
+
+    #[test]
+    fn test_int_columns() {
+        let content = "#table(columns: 2, fill: red, [a], [b], [c], [d])\n";
+        assert_eq!(
+            format(content, Config::new()),
+            "#table(\n  columns: 2,\n  fill: red,\n  [a], [b],\n  [c], [d],\n)\n"
+        );
+
+        let content = "#table(columns: 0, [a], [b])\n";
+        assert_eq!(format(content, Config::new()), content);
+    }
+
+    #[test]
+    fn test_extra_table_functions() {
+        let config =
+            Config::new().with_extra_table_functions(vec!["my-table".into(), "tbl".into()]);
+        let expected = "(\n  columns: 2,\n  [a], [b],\n  [c], [d],\n)\n";
+        for callee in ["my-table", "mypkg.tbl"] {
+            let content = format!("#{callee}(columns: 2, [a], [b], [c], [d])\n");
+            assert_eq!(
+                format(&content, config.clone()),
+                format!("#{callee}{expected}")
+            );
+        }
+
+        let content = "#my-table(columns: 2, [a], [b], [c], [d])\n";
+        assert_eq!(format(content, Config::new()), content);
+    }
+
+    #[test]
+    fn test_align_table_cells() {
+        let content = "#table(columns: 3, [a], [bbb], [c], [dddd], [e], [f])\n";
+        assert_eq!(
+            format(content, Config::new().with_align_table_cells(true)),
+            "#table(\n  columns: 3,\n  [a],    [bbb], [c],\n  [dddd], [e],   [f],\n)\n"
+        );
+
+        let content = "#table(columns: 2, [a], {\n  let x = 1\n  x\n}, [ccc], [d])\n";
+        assert_eq!(
+            format(content, Config::new().with_align_table_cells(true)),
+            format(content, Config::new())
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use ecow::EcoString;
 use typst_syntax::{ast::*, SyntaxKind, SyntaxNode};
 
+use crate::MathSymbolStyle;
+
 pub fn is_only_one_and<T>(
     mut iterator: impl Iterator<Item = T>,
     f: impl FnOnce(&T) -> bool,
@@ -32,6 +34,44 @@ pub(super) fn func_name(node: FuncCall<'_>) -> EcoString {
     node.callee().to_untyped().clone().into_text()
 }
 
+/// Whether the args have rows separated by semicolons, like `mat(1, 2; 3, 4)` in math.
+pub(super) fn is_2d_arg(args: Args<'_>) -> bool {
+    args.to_untyped()
+        .children()
+        .any(|child| child.kind() == SyntaxKind::Semicolon)
+}
+
+/// Whether the items among the children are separated by exactly one separator each, without
+/// empty items or a trailing separator, like `(a, b)` but not `(a,, b)` or `(a, b,)`. Such items
+/// can be laid out again without changing their meaning. Spaces and parens are skipped.
+pub(super) fn is_separated_list(node: &SyntaxNode, is_sep: impl Fn(SyntaxKind) -> bool) -> bool {
+    let mut expects_item = true;
+    for child in node.children() {
+        match child.kind() {
+            SyntaxKind::Space | SyntaxKind::LeftParen | SyntaxKind::RightParen => {}
+            kind if is_sep(kind) => {
+                if expects_item {
+                    return false;
+                }
+                expects_item = true;
+            }
+            // An empty arg is parsed as an empty `Math`.
+            _ if expects_item && !child.is_empty() => expects_item = false,
+            _ => return false,
+        }
+    }
+    !expects_item
+}
+
+/// Whether the last item before the closing paren is followed by a comma, like `(1, 2,)`.
+pub(super) fn has_trailing_comma(node: &SyntaxNode) -> bool {
+    node.children()
+        .take_while(|child| child.kind() != SyntaxKind::RightParen)
+        .filter(|child| !child.kind().is_trivia())
+        .last()
+        .is_some_and(|child| child.kind() == SyntaxKind::Comma)
+}
+
 /// Like `f()`, `f(x, y)`, not `f[]`
 pub(super) fn has_parenthesized_args(node: Args<'_>) -> bool {
     node.to_untyped()
@@ -68,3 +108,249 @@ pub(super) fn has_additional_args(node: Args<'_>) -> bool {
         .filter_map(|node| node.cast::<'_, Arg>());
     args.count() > 1
 }
+
+/// Replace unicode escapes in a string literal with literal characters, if it is safe.
+pub(super) fn normalize_str_escapes(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('\\') {
+        res.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let unicode_escape = if let Some(hex) = rest.strip_prefix("\\u{") {
+            hex.find('}').map(|end| {
+                let ch = u32::from_str_radix(&hex[..end], 16)
+                    .ok()
+                    .and_then(char::from_u32);
+                (ch, end + 4)
+            })
+        } else {
+            None
+        };
+        match unicode_escape {
+            Some((Some(ch), len)) if !is_escape_needed(ch) => {
+                res.push(ch);
+                rest = &rest[len..];
+            }
+            _ => {
+                // Keep other escapes, including the escaped character.
+                let len = rest[1..].chars().next().map_or(1, |c| 1 + c.len_utf8());
+                res.push_str(&rest[..len]);
+                rest = &rest[len..];
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+fn is_escape_needed(ch: char) -> bool {
+    matches!(ch, '"' | '\\')
+        || ch.is_control()
+        || (ch.is_whitespace() && ch != ' ')
+        || is_invisible(ch)
+}
+
+/// Checks if a character is a format character (category `Cf`) or otherwise default-ignorable,
+/// such as zero-width spaces, joiners, bidi controls, variation selectors, and the BOM, which are
+/// invisible when written literally.
+fn is_invisible(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{AD}'
+            | '\u{34F}'
+            | '\u{600}'..='\u{605}'
+            | '\u{61C}'
+            | '\u{6DD}'
+            | '\u{70F}'
+            | '\u{890}'..='\u{891}'
+            | '\u{8E2}'
+            | '\u{115F}'..='\u{1160}'
+            | '\u{17B4}'..='\u{17B5}'
+            | '\u{180B}'..='\u{180F}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{206F}'
+            | '\u{3164}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FEFF}'
+            | '\u{FFA0}'
+            | '\u{FFF0}'..='\u{FFFB}'
+            | '\u{110BD}'
+            | '\u{110CD}'
+            | '\u{13430}'..='\u{1343F}'
+            | '\u{1BCA0}'..='\u{1BCA3}'
+            | '\u{1D173}'..='\u{1D17A}'
+            | '\u{E0000}'..='\u{E0FFF}'
+    )
+}
+
+/// Remove redundant leading zeros of a decimal number, keeping the rest verbatim, e.g., `050%` to
+/// `50%` and `007.5e3` to `7.5e3`. Returns `None` if nothing changes.
+pub(super) fn normalize_number(text: &str) -> Option<String> {
+    if ["0x", "0o", "0b"]
+        .iter()
+        .any(|prefix| text.starts_with(prefix))
+    {
+        return None;
+    }
+    let digits = text.bytes().take_while(u8::is_ascii_digit).count();
+    let trimmed = text[..digits].trim_start_matches('0');
+    // Keep a single zero, as in `0.5`.
+    let zeros = (digits - trimmed.len()).min(digits.saturating_sub(1));
+    (zeros > 0).then(|| text[zeros..].to_string())
+}
+
+/// Get the single atom in parentheses, such as `(2)` or `(alpha)`.
+/// Removing the parentheses of a math script like this does not change its meaning.
+pub(super) fn get_parenthesized_math_atom(expr: Expr<'_>) -> Option<Expr<'_>> {
+    // The parser turns the parentheses of a script into `LeftParen` and `RightParen`.
+    let Expr::Math(math) = expr else {
+        return None;
+    };
+    let [open, body, close] = math.to_untyped().children().as_slice() else {
+        return None;
+    };
+    if open.kind() != SyntaxKind::LeftParen || close.kind() != SyntaxKind::RightParen {
+        return None;
+    }
+    let mut children = body.children();
+    let atom = children.next()?.cast::<Expr>()?;
+    if children.next().is_some() {
+        return None;
+    }
+    let is_atom = match atom {
+        Expr::MathIdent(_) => true,
+        Expr::MathText(text) => {
+            let text = text.to_untyped().text();
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c.is_alphanumeric(),
+                _ => text.chars().all(|c| c.is_ascii_digit()),
+            }
+        }
+        _ => false,
+    };
+    is_atom.then_some(atom)
+}
+
+/// Check whether the node is a binary operator or relation in math, given its preceding siblings.
+/// Prefix operators like `-x` are not binary.
+pub(super) fn is_math_binary_operator(node: &SyntaxNode, preceding: &[SyntaxNode]) -> bool {
+    if !is_math_operator(node) {
+        return false;
+    }
+    if !matches!(node.text().as_str(), "+" | "-" | "±" | "∓") {
+        return true;
+    }
+    preceding
+        .iter()
+        .rev()
+        .find(|prev| prev.kind() != SyntaxKind::Space)
+        .is_some_and(|prev| {
+            !(is_math_operator(prev)
+                || matches!(
+                    prev.kind(),
+                    SyntaxKind::Linebreak | SyntaxKind::MathAlignPoint
+                )
+                || matches!(prev.text().as_str(), "," | ";" | ":"))
+        })
+}
+
+fn is_math_operator(node: &SyntaxNode) -> bool {
+    matches!(
+        node.kind(),
+        SyntaxKind::MathText | SyntaxKind::MathShorthand
+    ) && matches!(
+        node.text().as_str(),
+        "+" | "-"
+            | "*"
+            | "="
+            | "<"
+            | ">"
+            | "±"
+            | "∓"
+            | "×"
+            | "·"
+            | "÷"
+            | "≠"
+            | "≤"
+            | "≥"
+            | "≈"
+            | "≡"
+            | "!="
+            | "<="
+            | ">="
+            | "=="
+            | ":="
+            | "::="
+            | "=:"
+            | "->"
+            | "<-"
+            | "<->"
+            | "=>"
+            | "<=>"
+            | "==>"
+            | "<=="
+            | "<==>"
+            | "-->"
+            | "<--"
+            | "<<"
+            | ">>"
+    )
+}
+
+/// The Unicode symbols and names of the shorthands in math.
+/// Delimiters like `[|` are left out, as their names are not matched as delimiters.
+const MATH_SHORTHAND_SYMBOLS: &[(&str, &str, &str)] = &[
+    ("...", "…", "dots.h"),
+    ("-", "−", "minus"),
+    ("*", "∗", "ast.op"),
+    ("!=", "≠", "eq.not"),
+    (":=", "≔", "colon.eq"),
+    ("::=", "⩴", "colon.double.eq"),
+    ("=:", "≕", "eq.colon"),
+    ("<<", "≪", "lt.double"),
+    ("<<<", "⋘", "lt.triple"),
+    (">>", "≫", "gt.double"),
+    (">>>", "⋙", "gt.triple"),
+    ("<=", "≤", "lt.eq"),
+    (">=", "≥", "gt.eq"),
+    ("->", "→", "arrow.r"),
+    ("-->", "⟶", "arrow.r.long"),
+    ("|->", "↦", "arrow.r.bar"),
+    (">->", "↣", "arrow.r.tail"),
+    ("->>", "↠", "arrow.r.twohead"),
+    ("<-", "←", "arrow.l"),
+    ("<--", "⟵", "arrow.l.long"),
+    ("<-<", "↢", "arrow.l.tail"),
+    ("<<-", "↞", "arrow.l.twohead"),
+    ("<->", "↔", "arrow.l.r"),
+    ("<-->", "⟷", "arrow.l.r.long"),
+    ("~>", "⇝", "arrow.r.squiggly"),
+    ("~~>", "⟿", "arrow.r.long.squiggly"),
+    ("<~", "⇜", "arrow.l.squiggly"),
+    ("<~~", "⬳", "arrow.l.long.squiggly"),
+    ("=>", "⇒", "arrow.r.double"),
+    ("|=>", "⤇", "arrow.r.double.bar"),
+    ("==>", "⟹", "arrow.r.double.long"),
+    ("<==", "⟸", "arrow.l.double.long"),
+    ("<=>", "⇔", "arrow.l.r.double"),
+    ("<==>", "⟺", "arrow.l.r.double.long"),
+];
+
+/// Get the text a math shorthand is rewritten to in the given style.
+/// Returns `None` if the node is not a recognized shorthand or should be kept as is.
+pub(super) fn restyle_math_shorthand(
+    node: &SyntaxNode,
+    style: MathSymbolStyle,
+) -> Option<&'static str> {
+    node.cast::<MathShorthand>()?;
+    let &(_, symbol, name) = MATH_SHORTHAND_SYMBOLS
+        .iter()
+        .find(|(shorthand, ..)| *shorthand == node.text().as_str())?;
+    match style {
+        MathSymbolStyle::Preserve => None,
+        MathSymbolStyle::Unicode => Some(symbol),
+        MathSymbolStyle::Named => Some(name),
+    }
+}
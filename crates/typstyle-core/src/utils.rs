@@ -1,16 +1,107 @@
-use std::ops::Range;
+use std::{io, ops::Range};
 
-/// Strip trailing whitespace in each line of the input string.
-pub fn strip_trailing_whitespace(s: &str) -> String {
-    if s.is_empty() {
-        return "\n".to_string();
+use pretty::{Render, RenderAnnotated};
+use typst_syntax::{SyntaxKind, SyntaxNode};
+
+use crate::pretty::Annotation;
+
+/// A renderer that strips trailing whitespace in each line and converts line endings, while
+/// passing the text through to an `io::Write` line by line.
+///
+/// Trailing whitespace in text annotated with [`Annotation::KeepTrailingWhitespace`] is kept.
+pub struct LineWriter<'n, W> {
+    writer: W,
+    newline: &'n str,
+    /// The current line, which is not written yet.
+    line: String,
+    /// The length of the start of the current line that must be kept as is.
+    kept_len: usize,
+    /// The annotations of the text being rendered.
+    annotations: Vec<Annotation>,
+    /// The number of linebreaks after the written text, held back until more text comes.
+    pending_newlines: usize,
+}
+
+impl<'n, W: io::Write> LineWriter<'n, W> {
+    pub fn new(writer: W, newline: &'n str) -> Self {
+        Self {
+            writer,
+            newline,
+            line: String::new(),
+            kept_len: 0,
+            annotations: vec![],
+            pending_newlines: 0,
+        }
+    }
+
+    /// Write the remaining text, ending with newlines only if `final_newline` is set.
+    pub fn finish(mut self, final_newline: bool) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.end_line()?;
+        }
+        if final_newline {
+            // An empty output still has one line.
+            for _ in 0..self.pending_newlines.max(1) {
+                self.writer.write_all(self.newline.as_bytes())?;
+            }
+        }
+        self.writer.flush()
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        let line = &self.line[..self.line.trim_end().len().max(self.kept_len)];
+        if !line.is_empty() {
+            for _ in 0..self.pending_newlines {
+                self.writer.write_all(self.newline.as_bytes())?;
+            }
+            self.writer.write_all(line.as_bytes())?;
+            self.pending_newlines = 0;
+        }
+        self.pending_newlines += 1;
+        self.line.clear();
+        self.kept_len = 0;
+        Ok(())
+    }
+
+    fn push_line(&mut self, text: &str) {
+        self.line.push_str(text);
+        if self
+            .annotations
+            .contains(&Annotation::KeepTrailingWhitespace)
+        {
+            self.kept_len = self.line.len();
+        }
     }
-    let mut res = String::with_capacity(s.len());
-    for line in s.lines() {
-        res.push_str(line.trim_end());
-        res.push('\n');
+}
+
+impl<W: io::Write> Render for LineWriter<'_, W> {
+    type Error = io::Error;
+
+    fn write_str(&mut self, s: &str) -> io::Result<usize> {
+        let mut lines = s.split('\n');
+        self.push_line(lines.next().unwrap_or_default());
+        for line in lines {
+            self.end_line()?;
+            self.push_line(line);
+        }
+        Ok(s.len())
+    }
+
+    fn fail_doc(&self) -> io::Error {
+        io::Error::other("failed to render the document")
+    }
+}
+
+impl<W: io::Write> RenderAnnotated<'_, Annotation> for LineWriter<'_, W> {
+    fn push_annotation(&mut self, annotation: &Annotation) -> io::Result<()> {
+        self.annotations.push(*annotation);
+        Ok(())
+    }
+
+    fn pop_annotation(&mut self) -> io::Result<()> {
+        self.annotations.pop();
+        Ok(())
     }
-    res
 }
 
 /// Get the range of the string obtained from trimming in the original string.
@@ -39,21 +130,167 @@ pub fn count_spaces_after_last_newline(s: &str, i: usize) -> usize {
     }
 }
 
+/// Check whether two syntax trees have the same meaning.
+///
+/// Nodes are compared by their kinds and children, and leaves also by their texts, except that:
+/// - Comments and commas are ignored, and so are semicolons in code.
+/// - Spaces are significant only in markup, equations, and math, where their contents are
+///   ignored, e.g., a space is equal to a line break. Spaces around the whole document are
+///   ignored.
+/// - Texts in markup are compared together with the spaces between them, as reflowing prose can
+///   merge or split text nodes.
+/// - Paragraph breaks are significant.
+pub fn is_semantically_equal(a: &SyntaxNode, b: &SyntaxNode) -> bool {
+    enum Significant<'a> {
+        Node(&'a SyntaxNode),
+        /// Texts and spaces, with each run of whitespace collapsed into a single space.
+        Text(String),
+    }
+
+    fn significant_children(node: &SyntaxNode, is_root: bool) -> Vec<Significant<'_>> {
+        let kind = node.kind();
+        let keeps_spaces = matches!(
+            kind,
+            SyntaxKind::Markup | SyntaxKind::Equation | SyntaxKind::Math
+        );
+        let mut children = vec![];
+        let push_text = |children: &mut Vec<Significant>, text: &str| match children.last_mut() {
+            Some(Significant::Text(last)) => last.push_str(text),
+            _ => children.push(Significant::Text(text.to_string())),
+        };
+        for child in node.children() {
+            match child.kind() {
+                SyntaxKind::LineComment | SyntaxKind::BlockComment | SyntaxKind::Comma => {}
+                SyntaxKind::Semicolon if kind == SyntaxKind::Code => {}
+                SyntaxKind::Space if keeps_spaces => push_text(&mut children, " "),
+                SyntaxKind::Space => {}
+                SyntaxKind::Text if kind == SyntaxKind::Markup => {
+                    push_text(&mut children, child.text())
+                }
+                _ => children.push(Significant::Node(child)),
+            }
+        }
+        for child in &mut children {
+            if let Significant::Text(text) = child {
+                *text = collapse_whitespace(text);
+            }
+        }
+        if is_root {
+            if let Some(Significant::Text(text)) = children.first_mut() {
+                *text = text.trim_start().to_string();
+            }
+            if let Some(Significant::Text(text)) = children.last_mut() {
+                *text = text.trim_end().to_string();
+            }
+            children.retain(|child| !matches!(child, Significant::Text(text) if text.is_empty()));
+        }
+        children
+    }
+
+    fn is_equal(a: &SyntaxNode, b: &SyntaxNode, is_root: bool) -> bool {
+        if a.kind() != b.kind() {
+            return false;
+        }
+        if a.children().as_slice().is_empty() && b.children().as_slice().is_empty() {
+            return a.kind() == SyntaxKind::Space || a.text() == b.text();
+        }
+        let a = significant_children(a, is_root);
+        let b = significant_children(b, is_root);
+        a.len() == b.len()
+            && a.iter().zip(&b).all(|pair| match pair {
+                (Significant::Node(a), Significant::Node(b)) => is_equal(a, b, false),
+                (Significant::Text(a), Significant::Text(b)) => a == b,
+                _ => false,
+            })
+    }
+
+    is_equal(a, b, true)
+}
+
+/// Replace each run of whitespace with a single space.
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    for c in text.chars() {
+        if !c.is_whitespace() {
+            collapsed.push(c);
+        } else if !collapsed.ends_with(' ') {
+            collapsed.push(' ');
+        }
+    }
+    collapsed
+}
+
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
+
     use super::*;
 
     #[test]
-    fn test_strip_trailing_whitespace() {
-        let s = strip_trailing_whitespace("");
-        assert_eq!(s, "\n");
-        let s = strip_trailing_whitespace(" ");
-        assert_eq!(s, "\n");
-        let s = strip_trailing_whitespace("\n");
-        assert_eq!(s, "\n");
-        let s = strip_trailing_whitespace(" \n - \n");
-        assert_eq!(s, "\n -\n");
-        let s = strip_trailing_whitespace(" \n - \n ");
-        assert_eq!(s, "\n -\n\n");
+    fn test_line_writer() {
+        let write = |newline: &str, pieces: &[(&str, bool)], final_newline: bool| {
+            let mut out = vec![];
+            let mut writer = LineWriter::new(&mut out, newline);
+            for &(piece, keep) in pieces {
+                if keep {
+                    writer
+                        .push_annotation(&Annotation::KeepTrailingWhitespace)
+                        .unwrap();
+                }
+                writer.write_str_all(piece).unwrap();
+                if keep {
+                    writer.pop_annotation().unwrap();
+                }
+            }
+            writer.finish(final_newline).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        // The cases of `strip_trailing_whitespace`, which this writer replaced.
+        for (s, expected) in [
+            ("", "\n"),
+            (" ", "\n"),
+            ("\n", "\n"),
+            (" \n - \n", "\n -\n"),
+            (" \n - \n ", "\n -\n\n"),
+            ("a\n\n\nb  \n\n", "a\n\n\nb\n\n"),
+        ] {
+            // Split the text, as documents are rendered in pieces.
+            let pieces = s
+                .split_inclusive(' ')
+                .map(|piece| (piece, false))
+                .collect_vec();
+            for newline in ["\n", "\r\n"] {
+                let expected = expected.replace('\n', newline);
+                assert_eq!(write(newline, &pieces, true), expected, "{s:?}");
+                let expected = expected.trim_end_matches(newline);
+                assert_eq!(write(newline, &pieces, false), expected, "{s:?}");
+            }
+        }
+        // Annotated trailing whitespace is kept.
+        assert_eq!(
+            write("\n", &[(" - ", true), ("\n - \n", false)], true),
+            " - \n -\n"
+        );
+        assert_eq!(
+            write(
+                "\r\n",
+                &[(" - ", false), ("a  ", true), ("\n - \n", false)],
+                true
+            ),
+            " - a  \r\n -\r\n"
+        );
+    }
+
+    #[test]
+    fn test_is_semantically_equal() {
+        let is_equal = |a: &str, b: &str| {
+            is_semantically_equal(&typst_syntax::parse(a), &typst_syntax::parse(b))
+        };
+        assert!(is_equal("foo\nbar // c\n", "foo bar\n"));
+        assert!(is_equal("#{ a; b }", "#{\n  a\n  b\n}"));
+        assert!(is_equal("$a  +  b$", "$a + b$"));
+        assert!(!is_equal("$a b$", "$ab$"));
+        assert!(!is_equal("$mat(1, 2; 3, 4)$", "$mat(1, 2, 3, 4)$"));
+        assert!(!is_equal("foo\n\nbar", "foo bar"));
     }
 }
@@ -23,6 +23,15 @@ pub struct CliArguments {
     #[arg(long, default_value_t = false, global = true)]
     pub check: bool,
 
+    /// Print a unified diff of the changes instead of writing them. Exits with 1 if formatting is required.
+    #[arg(
+        long,
+        default_value_t = false,
+        global = true,
+        conflicts_with_all = ["inplace", "check"]
+    )]
+    pub diff: bool,
+
     #[command(flatten)]
     pub style: StyleArgs,
 
@@ -65,25 +74,19 @@ pub enum Command {
 
 #[derive(Args)]
 pub struct StyleArgs {
-    /// The column width of the output
+    /// The column width of the output. Overrides `max_width` in `typstyle.toml` [default: 80]
     #[arg(
         short,
         long,
-        default_value_t = 80,
+        visible_alias = "width",
         global = true,
         help_heading = "Format Configuration"
     )]
-    pub column: usize,
+    pub column: Option<usize>,
 
-    /// Spaces per level of indentation in the output
-    #[arg(
-        short,
-        long,
-        default_value_t = 2,
-        global = true,
-        help_heading = "Format Configuration"
-    )]
-    pub tab_width: usize,
+    /// Spaces per level of indentation in the output. Overrides `tab_spaces` in `typstyle.toml` [default: 2]
+    #[arg(short, long, global = true, help_heading = "Format Configuration")]
+    pub tab_width: Option<usize>,
 }
 
 #[derive(Args)]
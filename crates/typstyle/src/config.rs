@@ -0,0 +1,47 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use log::debug;
+
+use typstyle_core::Config;
+
+use crate::cli::StyleArgs;
+
+/// The name of the configuration file to look up.
+const CONFIG_FILE_NAME: &str = "typstyle.toml";
+
+/// Resolves the formatting configuration.
+///
+/// Options are read from the nearest `typstyle.toml` in the current directory or its ancestors,
+/// if any. Options explicitly given on the command line take precedence over the file.
+pub fn resolve_config(args: &StyleArgs) -> Result<Config> {
+    let cwd = std::env::current_dir().context("failed to get the current directory")?;
+    let mut config = match find_config_file(&cwd) {
+        Some(path) => {
+            debug!("Using config file: {}", path.display());
+            load_config_file(&path)?
+        }
+        None => Config::default(),
+    };
+
+    if let Some(column) = args.column {
+        config.max_width = column;
+    }
+    if let Some(tab_width) = args.tab_width {
+        config.tab_spaces = tab_width;
+    }
+    Ok(config)
+}
+
+/// Finds the config file in the given directory or its ancestors.
+fn find_config_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+fn load_config_file(path: &Path) -> Result<Config> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    Config::from_toml_str(&content).with_context(|| format!("failed to parse {}", path.display()))
+}
@@ -5,7 +5,8 @@ use std::{
 };
 
 use anyhow::{bail, Context, Result};
-use log::{debug, error, info, warn};
+use log::{error, info};
+use similar::TextDiff;
 use typst_syntax::Source;
 use walkdir::{DirEntry, WalkDir};
 
@@ -13,19 +14,19 @@ use typstyle_core::{Config, Typstyle};
 
 use crate::cli::CliArguments;
 
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum FormatStatus {
+    /// The content was already well-formatted and unchanged.
+    Unchanged,
     /// The content was changed (and written back to the file if needed).
     Changed,
-    /// The content was already well-formatted and unchanged, or erroneous.
-    Unchanged,
+    /// The content has syntax errors and was left unchanged.
+    Erroneous,
 }
 
 impl std::ops::BitOrAssign for FormatStatus {
     fn bitor_assign(&mut self, rhs: Self) {
-        match (&self, rhs) {
-            (Self::Unchanged, FormatStatus::Unchanged) => *self = Self::Unchanged,
-            _ => *self = Self::Changed,
-        }
+        *self = (*self).max(rhs);
     }
 }
 
@@ -38,10 +39,16 @@ impl std::ops::BitOrAssign for FormatStatus {
 /// - `directory`: An optional path to the directory containing `.typ` files. If `None`, the
 ///   current working directory is used.
 /// - `args`: CLI arguments.
+/// - `config`: The formatting configuration.
 ///
 /// # Returns
-/// Returns `Ok(FormatStatus)` indicating whether any file was modified.
-pub fn format_all(directory: &Option<PathBuf>, args: &CliArguments) -> Result<FormatStatus> {
+/// Returns `Ok(FormatStatus)` indicating whether any file was modified or erroneous. Erroneous
+/// files are left unchanged, and their syntax errors are reported.
+pub fn format_all(
+    directory: &Option<PathBuf>,
+    args: &CliArguments,
+    config: &Config,
+) -> Result<FormatStatus> {
     let mut status = FormatStatus::Unchanged;
 
     let directory = directory
@@ -70,23 +77,25 @@ pub fn format_all(directory: &Option<PathBuf>, args: &CliArguments) -> Result<Fo
         let Ok(content) = std::fs::read_to_string(entry.path()) else {
             continue;
         };
-        let cfg = Config::new()
-            .with_width(args.style.column)
-            .with_tab_spaces(args.style.tab_width);
-        let Ok(res) = Typstyle::new(cfg).format_content(&content) else {
-            warn!("Failed to format: {}", entry.path().display());
-            continue;
+        let res = match format_debug(&content, args, config) {
+            FormatResult::Changed(res) => res,
+            FormatResult::Unchanged(_) => {
+                summary.unchanged_count += 1;
+                continue;
+            }
+            FormatResult::Erroneous { errors } => {
+                report_errors(&entry.path().display().to_string(), &errors);
+                status |= FormatStatus::Erroneous;
+                continue;
+            }
         };
-
-        // Check if the content is already well-formatted (unchanged)
-        if res == content {
-            summary.unchanged_count += 1;
-            continue;
-        }
-        status = FormatStatus::Changed;
+        status |= FormatStatus::Changed;
 
         if args.check {
-            debug!("Would reformat: {}", entry.path().display());
+            info!("Would reformat: {}", entry.path().display());
+            summary.format_count += 1
+        } else if args.diff {
+            print_diff(&entry.path().display().to_string(), &content, &res);
             summary.format_count += 1
         } else {
             // Attempt to overwrite the file with the formatted content
@@ -101,7 +110,7 @@ pub fn format_all(directory: &Option<PathBuf>, args: &CliArguments) -> Result<Fo
     }
     let duration = start_time.elapsed();
 
-    if args.check {
+    if args.check || args.diff {
         info!(
             "{} would be reformatted ({} already formatted), checked in {:?}",
             num_files(summary.format_count),
@@ -135,16 +144,21 @@ pub fn format_all(directory: &Option<PathBuf>, args: &CliArguments) -> Result<Fo
 /// # Parameters
 /// - `input`: A list of paths to `.typ` files to be formatted.
 /// - `args`: CLI arguments.
+/// - `config`: The formatting configuration.
 ///
 /// # Returns
 /// Returns `Ok(FormatStatus)` indicating whether any file was modified.
-pub fn format_many(input: &[PathBuf], args: &CliArguments) -> Result<FormatStatus> {
+pub fn format_many(
+    input: &[PathBuf],
+    args: &CliArguments,
+    config: &Config,
+) -> Result<FormatStatus> {
     // In case of multiple files, process them in order without failing
     let mut status = FormatStatus::Unchanged;
     let mut error_count = 0;
     // Format the files one by one
     for file in input {
-        status |= format_one(Some(file), args).unwrap_or_else(|e| {
+        status |= format_one(Some(file), args, config).unwrap_or_else(|e| {
             error!("{e}");
             error_count += 1;
             FormatStatus::Unchanged
@@ -168,15 +182,21 @@ pub fn format_many(input: &[PathBuf], args: &CliArguments) -> Result<FormatStatu
 /// # Parameters
 /// - `input`: An optional path to a `.typ` file to be formatted. If `None`, input is read from stdin.
 /// - `args`: CLI arguments.
+/// - `config`: The formatting configuration.
 ///
 /// # Returns
 /// Returns `Ok(FormatStatus)` indicating whether the file was modified or remained unchanged.
-pub fn format_one(input: Option<&PathBuf>, args: &CliArguments) -> Result<FormatStatus> {
+pub fn format_one(
+    input: Option<&PathBuf>,
+    args: &CliArguments,
+    config: &Config,
+) -> Result<FormatStatus> {
     let content = get_input(input)?;
-    let res = format_debug(content, args);
+    let res = format_debug(&content, args, config);
     let status = match &res {
         FormatResult::Changed(_) => FormatStatus::Changed,
-        _ => FormatStatus::Unchanged,
+        FormatResult::Unchanged(_) => FormatStatus::Unchanged,
+        FormatResult::Erroneous { .. } => FormatStatus::Erroneous,
     };
     match res {
         FormatResult::Changed(res) if args.inplace => {
@@ -188,23 +208,19 @@ pub fn format_one(input: Option<&PathBuf>, args: &CliArguments) -> Result<Format
                 info!("Would reformat: {}", path.display());
             }
         }
+        FormatResult::Changed(res) if args.diff => {
+            print_diff(&input_name(input), &content, &res);
+        }
         FormatResult::Changed(res) | FormatResult::Unchanged(res) => {
-            if !args.inplace && !args.check {
+            if !args.inplace && !args.check && !args.diff {
                 print!("{}", res);
             }
         }
-        FormatResult::Erroneous(content) => {
-            if !args.inplace && !args.check {
+        FormatResult::Erroneous { errors } => {
+            if !args.inplace && !args.check && !args.diff {
                 print!("{}", content); // still prints the original content to enable piping
             }
-            if let Some(path) = input {
-                warn!(
-                    "Failed to parse {}. The source is erroneous.",
-                    path.display()
-                );
-            } else {
-                warn!("Failed to parse stdin. The source is erroneous.");
-            }
+            report_errors(&input_name(input), &errors);
         }
     }
     Ok(status)
@@ -213,26 +229,38 @@ pub fn format_one(input: Option<&PathBuf>, args: &CliArguments) -> Result<Format
 enum FormatResult {
     Changed(String),
     Unchanged(String),
-    Erroneous(String),
+    Erroneous {
+        /// Syntax errors in the form of `line:column: message`.
+        errors: Vec<String>,
+    },
 }
 
-fn format_debug(content: String, args: &CliArguments) -> FormatResult {
-    let source = Source::detached(&content);
+fn format_debug(content: &str, args: &CliArguments, config: &Config) -> FormatResult {
+    let source = Source::detached(content);
     let root = source.root();
     if args.debug.ast {
         println!("{:#?}", root);
     }
 
-    let config = Config::new()
-        .with_width(args.style.column)
-        .with_tab_spaces(args.style.tab_width);
-    let res = match Typstyle::new(config).format_source_inspect(&source, |doc| {
+    let res = match Typstyle::new(config.clone()).format_source_inspect(&source, |doc| {
         if args.debug.pretty_doc {
             println!("{:#?}", doc);
         }
     }) {
         Ok(res) => res,
-        Err(_) => return FormatResult::Erroneous(content),
+        Err(_) => {
+            let errors = root
+                .errors()
+                .into_iter()
+                .map(|err| {
+                    let start = source.range(err.span).map_or(0, |range| range.start);
+                    let line = source.byte_to_line(start).unwrap_or_default() + 1;
+                    let column = source.byte_to_column(start).unwrap_or_default() + 1;
+                    format!("{line}:{column}: {}", err.message)
+                })
+                .collect();
+            return FormatResult::Erroneous { errors };
+        }
     };
 
     // Compare `res` with `content` to perform CI checks
@@ -243,6 +271,24 @@ fn format_debug(content: String, args: &CliArguments) -> FormatResult {
     }
 }
 
+/// Logs the syntax errors of an erroneous source.
+fn report_errors(name: &str, errors: &[String]) {
+    error!("Failed to parse {name}. The source is erroneous.");
+    for err in errors {
+        error!("{name}:{err}");
+    }
+}
+
+/// Prints a unified diff between the original and the formatted content, compared line by line.
+fn print_diff(name: &str, original: &str, formatted: &str) {
+    let diff = TextDiff::from_lines(original, formatted);
+    print!("{}", diff.unified_diff().header(name, name));
+}
+
+fn input_name(input: Option<&PathBuf>) -> String {
+    input.map_or_else(|| "stdin".to_string(), |path| path.display().to_string())
+}
+
 fn get_input(input: Option<&PathBuf>) -> Result<String> {
     match input {
         Some(path) => std::fs::read_to_string(path)
@@ -1,4 +1,5 @@
 mod cli;
+mod config;
 mod fmt;
 mod logging;
 
@@ -9,7 +10,7 @@ use clap::Parser;
 use fmt::{format_all, format_many, format_one, FormatStatus};
 use log::error;
 
-use crate::cli::CliArguments;
+use crate::{cli::CliArguments, config::resolve_config};
 
 enum CliResults {
     Good,
@@ -38,10 +39,11 @@ fn main() -> CliResults {
         log::LevelFilter::Info
     });
 
-    // Should put the formatter into check mode
-    let check = args.check;
+    // Should put the formatter into check mode (diff mode reports like check mode)
+    let check = args.check || args.diff;
     match execute(args) {
         Ok(FormatStatus::Changed) if check => CliResults::Bad,
+        Ok(FormatStatus::Erroneous) => CliResults::Bad,
         Ok(_) => CliResults::Good,
         Err(e) => {
             error!("{e}");
@@ -54,7 +56,7 @@ fn execute(args: CliArguments) -> Result<FormatStatus> {
     if let Some(command) = &args.command {
         match command {
             cli::Command::FormatAll { directory } => {
-                return format_all(directory, &args);
+                return format_all(directory, &args, &resolve_config(&args.style)?);
             }
             #[cfg(feature = "completion")]
             cli::Command::Completions { shell } => {
@@ -72,9 +74,10 @@ fn execute(args: CliArguments) -> Result<FormatStatus> {
         }
     }
 
+    let config = resolve_config(&args.style)?;
     if args.input.is_empty() {
-        format_one(None, &args)
+        format_one(None, &args, &config)
     } else {
-        format_many(&args.input, &args)
+        format_many(&args.input, &args, &config)
     }
 }
@@ -58,12 +58,13 @@ fn test_one_erroneous() {
     space.write_tracked("a.typ", "#let");
 
     typstyle_cmd_snapshot!(space.cli().args(["a.typ"]), @r"
-    success: true
-    exit_code: 0
+    success: false
+    exit_code: 1
     ----- stdout -----
     #let
     ----- stderr -----
-    warn: Failed to parse a.typ. The source is erroneous.
+    error: Failed to parse a.typ. The source is erroneous.
+    error: a.typ:1:5: expected pattern
     ");
 
     assert!(space.all_unmodified());
@@ -75,12 +76,13 @@ fn test_one_inplace_erroneous() {
     space.write_tracked("a.typ", "#let");
 
     typstyle_cmd_snapshot!(space.cli().args(["a.typ", "-i"]), @r"
-    success: true
-    exit_code: 0
+    success: false
+    exit_code: 1
     ----- stdout -----
 
     ----- stderr -----
-    warn: Failed to parse a.typ. The source is erroneous.
+    error: Failed to parse a.typ. The source is erroneous.
+    error: a.typ:1:5: expected pattern
     ");
 
     assert!(space.all_unmodified());
@@ -102,6 +104,28 @@ fn test_one_check_quiet() {
     assert!(space.all_unmodified());
 }
 
+#[test]
+fn test_one_diff() {
+    let mut space = Workspace::new();
+    space.write_tracked("a.typ", "#let a  =  0\n#let b = 1\n");
+
+    typstyle_cmd_snapshot!(space.cli().args(["a.typ", "--diff"]), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    --- a.typ
+    +++ a.typ
+    @@ -1,2 +1,2 @@
+    -#let a  =  0
+    +#let a = 0
+     #let b = 1
+
+    ----- stderr -----
+    ");
+
+    assert!(space.all_unmodified());
+}
+
 #[test]
 fn test_two_0() {
     let mut space = Workspace::new();
@@ -114,6 +114,25 @@ fn test_all_1_check() {
     assert!(space.all_unmodified());
 }
 
+#[test]
+fn test_all_1_check_lists_files() {
+    let mut space = Workspace::new();
+    space.write_tracked("a.typ", "#let a = 0\n");
+    space.write_tracked("x/b.typ", "#let b  =  1");
+
+    typstyle_cmd_snapshot!(space.cli().args(["format-all", "--check"]), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    Would reformat: [TEMP_PATH]/project/x/b.typ
+    1 file would be reformatted (1 already formatted), checked in [DURATION]
+
+    ----- stderr -----
+    ");
+
+    assert!(space.all_unmodified());
+}
+
 #[test]
 fn test_all_2_check() {
     let mut space = Workspace::new();
@@ -139,16 +158,17 @@ fn test_all_erroneous() {
     let mut space = Workspace::new();
     space.write_tracked("a.typ", "#let a = 0\n");
     space.write_tracked("x/b.typ", "#let b  =  1");
-    space.write_tracked("x/y/c.typ", "#let c  =  2; #");
+    space.write_tracked("x/y/c.typ", "#let");
 
     typstyle_cmd_snapshot!(space.cli().args(["format-all", "-v"]), @r"
-    success: true
-    exit_code: 0
+    success: false
+    exit_code: 1
     ----- stdout -----
     Successfully formatted 1 file (1 unchanged) in [DURATION]
 
     ----- stderr -----
-    warn: Failed to format: [TEMP_PATH]/project/x/y/c.typ
+    error: Failed to parse [TEMP_PATH]/project/x/y/c.typ. The source is erroneous.
+    error: [TEMP_PATH]/project/x/y/c.typ:1:5: expected pattern
     ");
 
     assert!(space.is_unmodified("a.typ"));
@@ -161,7 +181,7 @@ fn test_all_erroneous_check() {
     let mut space = Workspace::new();
     space.write_tracked("a.typ", "#let a = 0\n");
     space.write_tracked("x/b.typ", "#let b  =  1");
-    space.write_tracked("x/y/c.typ", "#let c  =  2; #");
+    space.write_tracked("x/y/c.typ", "#let");
 
     typstyle_cmd_snapshot!(space.cli().args(["format-all", "--check", "-v"]), @r"
     success: false
@@ -171,7 +191,8 @@ fn test_all_erroneous_check() {
     1 file would be reformatted (1 already formatted), checked in [DURATION]
 
     ----- stderr -----
-    warn: Failed to format: [TEMP_PATH]/project/x/y/c.typ
+    error: Failed to parse [TEMP_PATH]/project/x/y/c.typ. The source is erroneous.
+    error: [TEMP_PATH]/project/x/y/c.typ:1:5: expected pattern
     ");
 
     assert!(space.all_unmodified());
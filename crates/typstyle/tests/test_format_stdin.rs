@@ -37,12 +37,13 @@ fn test_stdin_erroneous() {
     let space = Workspace::new();
 
     typstyle_cmd_snapshot!(space.cli().pass_stdin("#"), @r"
-    success: true
-    exit_code: 0
+    success: false
+    exit_code: 1
     ----- stdout -----
     #
     ----- stderr -----
-    warn: Failed to parse stdin. The source is erroneous.
+    error: Failed to parse stdin. The source is erroneous.
+    error: stdin:1:2: expected expression
     ");
 }
 
@@ -63,6 +64,56 @@ fn test_stdin_column() {
     ");
 }
 
+#[test]
+fn test_stdin_width() {
+    let space = Workspace::new();
+
+    typstyle_cmd_snapshot!(space.cli().args(["--width=0"]).pass_stdin(STDIN), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    #let x = (
+      1
+        + 2
+    )
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn test_stdin_config_file() {
+    let space = Workspace::new();
+    space.write("typstyle.toml", "max_width = 0\nindent = 4\n");
+
+    typstyle_cmd_snapshot!(space.cli().pass_stdin(STDIN), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    #let x = (
+        1
+            + 2
+    )
+
+    ----- stderr -----
+    ");
+}
+
+#[test]
+fn test_stdin_config_file_override() {
+    let space = Workspace::new();
+    space.write("typstyle.toml", "max_width = 0\n");
+
+    typstyle_cmd_snapshot!(space.cli().args(["-c=80"]).pass_stdin(STDIN), @r"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    #let x = (1 + 2)
+
+    ----- stderr -----
+    ");
+}
+
 #[test]
 fn test_stdin_check() {
     let space = Workspace::new();
@@ -76,6 +127,25 @@ fn test_stdin_check() {
     ");
 }
 
+#[test]
+fn test_stdin_diff() {
+    let space = Workspace::new();
+
+    typstyle_cmd_snapshot!(space.cli().args(["--diff"]).pass_stdin(STDIN), @r"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+    --- stdin
+    +++ stdin
+    @@ -1 +1 @@
+    -#let  x  = (1+2)
+    \ No newline at end of file
+    +#let x = (1 + 2)
+
+    ----- stderr -----
+    ");
+}
+
 #[test]
 fn test_stdin_inplace() {
     let space = Workspace::new();
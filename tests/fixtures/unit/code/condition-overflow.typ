@@ -0,0 +1,19 @@
+#if aaaaaaaaaa and bbbbbbbbbb and cccccccccc {
+  1
+}
+
+#while aaaaaaaaaa and bbbbbbbbbb and cccccccccc {
+  1
+}
+
+#if aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa {
+  1
+}
+
+#if not aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa {
+  1
+}
+
+#while "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa" {
+  1
+}
@@ -0,0 +1,6 @@
+#test[
+    ```typ
+    #let x = 1
+      #x
+    ```
+]
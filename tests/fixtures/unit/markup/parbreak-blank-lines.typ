@@ -0,0 +1,13 @@
+a
+
+
+
+
+b
+
+- c
+
+
+
+
+  d
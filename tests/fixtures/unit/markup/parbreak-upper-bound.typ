@@ -0,0 +1,8 @@
+Foo
+
+
+
+Bar
+
+
+Baz
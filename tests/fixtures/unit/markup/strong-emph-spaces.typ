@@ -0,0 +1,9 @@
+*bold*
+
+* not bold *
+
+_emph_
+
+_ not emph _
+
+*a* * b*
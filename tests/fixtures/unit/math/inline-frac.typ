@@ -0,0 +1,5 @@
+#let x = $(a + b + c) / (d + e + f)$
+
+$(a + b + c + d) / (e + f + g + h)$
+
+$ (a + b + c + d) / (e + f + g + h) $
@@ -0,0 +1,19 @@
+$ mat(1, 2, 3; 4, 5, 6; 7, 8, 9) $
+
+$
+  mat(
+    1, 2, 3;
+    4, 5, 6;
+    7, 8, 9
+  )
+$
+
+$ mat(delim: "[", 1, -2, 3; 40, 5, 600; 7, 8, 9) $
+
+$ mat(1, 2, 3; 4, 5, 6; 7, 8, 9; delim: "[") $
+
+$ mat(1, 2; 3, 4;) $
+
+$ mat(1,, 2; 3, 4) $
+
+$ mat(1, 2; 3, 4, delim: "[") $